@@ -1,6 +1,9 @@
 use crate::{
     auction::create_after_settle_message,
+    bid_strategy::validate_bid_strategy,
     exchange::swap,
+    lock::validate_lock_tiers,
+    referral::validate_referral_reward_bps,
     state::{read_swap_route, remove_swap_route, store_swap_route, CONFIG},
 };
 
@@ -33,6 +36,9 @@ pub fn update_config(
     new_config: InstantiateMsg,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     verify_sender_is_admin(deps.as_ref(), &sender)?;
+    validate_lock_tiers(&new_config.lock_tiers)?;
+    validate_referral_reward_bps(new_config.referral_reward_bps)?;
+    validate_bid_strategy(&new_config.bid_strategy)?;
 
     CONFIG.update(deps.storage, |mut c| -> Result<_, ContractError> {
         c.accepted_denom = new_config.accepted_denom;
@@ -42,6 +48,11 @@ pub fn update_config(
         c.withdraw_time_buffer_secs = new_config.withdraw_time_buffer;
         c.winning_bidder_reward_bps = new_config.winning_bidder_reward_bps;
         c.max_inj_offset_bps = new_config.max_inj_offset_bps;
+        c.lock_tiers = new_config.lock_tiers;
+        c.referral_reward_bps = new_config.referral_reward_bps;
+        c.reward_vesting_secs = new_config.reward_vesting_secs;
+        c.bid_strategy = new_config.bid_strategy;
+        c.valuation_policy = new_config.valuation_policy;
 
         Ok(c)
     })?;
@@ -164,6 +175,7 @@ pub fn manual_swap(
             denom: asset.to_string(),
             amount: amount.into(),
         },
+        round: 0,
     })?;
 
     let after_settle_msg = create_after_settle_message(
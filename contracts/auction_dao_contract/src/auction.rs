@@ -1,10 +1,20 @@
+use crate::bid_strategy::check_bid;
 use crate::exchange::{simulate, swap};
+use crate::lock::expire_locks_until;
+use crate::lp::pooled_funds;
+use crate::queue::create_process_queue_message;
 use crate::router::get_inj_value_asset;
+use crate::shutdown::ensure_not_shutdown;
 use crate::state::{
-    read_swap_route, BID_ATTEMPT, BID_ATTEMPT_TRANSIENT, CONFIG, SETTLED_AMOUNT_TRANSIENT,
+    read_swap_route, BID_ATTEMPT, BID_ATTEMPT_TRANSIENT, CONFIG, GLOBAL, SETTLED_AMOUNT_TRANSIENT,
+};
+use crate::stats::record_round_result;
+use auction_dao::msg::{
+    BidCapacityResponse, ExecuteMsg, SELL_ASSET_SUCCESS_REPLY_ID, TRY_BID_SUCCESS_REPLY_ID,
+};
+use auction_dao::state::{
+    BidAttempt, Config, Global, RoundRecord, SellAssetPayload, ValuationPolicy,
 };
-use auction_dao::msg::{ExecuteMsg, SELL_ASSET_SUCCESS_REPLY_ID, TRY_BID_SUCCESS_REPLY_ID};
-use auction_dao::state::{BidAttempt, Config, SellAssetPayload};
 
 use auction_dao::{error::ContractError, types::BidResult};
 use cosmwasm_std::{
@@ -84,7 +94,7 @@ pub(crate) fn get_auction_params(
 
 pub(crate) fn get_current_auction_value_using_router(
     deps: Deps<InjectiveQueryWrapper>,
-) -> StdResult<Uint128> {
+) -> Result<Uint128, ContractError> {
     let current_auction_basket_response = get_current_auction(deps)?;
 
     let basket_assets = current_auction_basket_response.amount;
@@ -98,7 +108,7 @@ pub(crate) fn get_current_auction_value_using_router(
             "inj".to_string(),
             asset.amount.clone(),
         )?;
-        total_inj_value = total_inj_value.checked_add(asset_inj_value)?;
+        total_inj_value += asset_inj_value;
     }
 
     Ok(total_inj_value)
@@ -135,6 +145,80 @@ pub fn get_current_auction_value_using_exchange(
     Ok(total_inj_value)
 }
 
+// Values the basket with the configured policy, used for bidding and for the max tokens
+pub(crate) fn get_basket_value(
+    deps: Deps<InjectiveQueryWrapper>,
+    config: &Config,
+) -> Result<Uint128, ContractError> {
+    match &config.valuation_policy {
+        ValuationPolicy::Router => get_current_auction_value_using_router(deps),
+        ValuationPolicy::Exchange => get_current_auction_value_using_exchange(deps),
+        policy => {
+            let router_value = get_current_auction_value_using_router(deps)?;
+            let exchange_value = get_current_auction_value_using_exchange(deps)?;
+
+            policy_value(policy, router_value, exchange_value)
+        }
+    }
+}
+
+pub(crate) fn policy_value(
+    policy: &ValuationPolicy,
+    router_value: Uint128,
+    exchange_value: Uint128,
+) -> Result<Uint128, ContractError> {
+    match policy {
+        ValuationPolicy::Router => Ok(router_value),
+        ValuationPolicy::Exchange => Ok(exchange_value),
+        ValuationPolicy::Min => Ok(router_value.min(exchange_value)),
+        ValuationPolicy::RejectOnDivergence { max_divergence_bps } => {
+            let divergence_bps = valuation_divergence_bps(router_value, exchange_value);
+            if divergence_bps > *max_divergence_bps {
+                return Err(ContractError::ValuationDivergence {
+                    router_value,
+                    exchange_value,
+                    divergence_bps,
+                    max_divergence_bps: *max_divergence_bps,
+                });
+            }
+
+            Ok(router_value.min(exchange_value))
+        }
+    }
+}
+
+pub(crate) fn valuation_divergence_bps(router_value: Uint128, exchange_value: Uint128) -> Uint128 {
+    let higher_value = router_value.max(exchange_value);
+    if higher_value.is_zero() {
+        return Uint128::zero();
+    }
+
+    router_value
+        .abs_diff(exchange_value)
+        .multiply_ratio(10000u128, higher_value)
+}
+
+/* The balance also holds the queued deposits, the requested withdrawals and the rewards
+not claimed yet, which are owed to the depositors. Only the pooled funds are bid */
+pub(crate) fn get_bid_capacity(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+    global: &Global,
+) -> Result<BidCapacityResponse, ContractError> {
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &config.accepted_denom)?
+        .amount;
+    let pooled_funds = pooled_funds(global);
+
+    Ok(BidCapacityResponse {
+        balance,
+        pooled_funds,
+        capacity: balance.min(pooled_funds),
+    })
+}
+
 pub(crate) fn try_bid(
     deps: DepsMut<InjectiveQueryWrapper>,
     env: Env,
@@ -163,6 +247,9 @@ pub(crate) fn try_bid(
 
     let config = CONFIG.load(deps.storage)?;
 
+    // no more bids once the DAO is winding down
+    ensure_not_shutdown(&config)?;
+
     // Check if it is time to bid
     // We want to push the bid as close to the end of the auction as possible
     if env
@@ -185,16 +272,33 @@ pub(crate) fn try_bid(
     }
     let min_bid_size = min_bid_size.to_uint_ceil();
 
-    //Get the value with the router
-    let basket_current_value = get_current_auction_value_using_router(deps.as_ref())?;
-
-    //Get the value from directly EXCHANGE module
-    // let basket_current_value = get_exchange_current_auction_value(deps.as_ref())?;
+    let basket_current_value = get_basket_value(deps.as_ref(), &config)?;
 
     if Uint256::from_uint128(basket_current_value) <= min_bid_size {
         return Err(ContractError::MinBidToHigh {});
     }
 
+    let bid_amount = Uint128::from_str(&min_bid_size.to_string())?;
+
+    // the locks of the rounds skipped or cleared since the last settlement end here
+    expire_locks_until(deps.storage, &env, round)?;
+    let global = GLOBAL.load(deps.storage)?;
+
+    check_bid(
+        &config,
+        bid_amount,
+        basket_current_value,
+        global.total_supply,
+    )?;
+
+    let capacity = get_bid_capacity(deps.as_ref(), &env, &config, &global)?.capacity;
+    if bid_amount > capacity {
+        return Err(ContractError::InsufficientBidCapacity {
+            bid: bid_amount,
+            capacity,
+        });
+    }
+
     let bid_msg = MsgBid {
         bid_amount: Some(ProstCoin {
             denom: config.accepted_denom,
@@ -218,7 +322,7 @@ pub(crate) fn try_bid(
         deps.storage,
         &BidAttempt {
             round,
-            amount: Uint128::from_str(&min_bid_size.to_string())?,
+            amount: bid_amount,
             submitted_by: info.sender,
             basket: current_auction.amount,
         },
@@ -265,20 +369,52 @@ pub fn try_settle(
     BID_ATTEMPT_TRANSIENT.remove(deps.storage);
     BID_ATTEMPT.remove(deps.storage);
 
+    let winning_bid = last_auction_result.amount.unwrap().amount;
+    let won = env.contract.address.as_str() == &last_auction_result.winner;
+    let config = CONFIG.load(deps.storage)?;
+
+    // a lost round keeps the current index, a won one is completed by the sells and the callback
+    let record = RoundRecord {
+        round: bid_attempt.round,
+        bid_amount: bid_attempt.amount,
+        keeper: bid_attempt.submitted_by.clone(),
+        basket: bid_attempt.basket.clone(),
+        result: if won { BidResult::Win } else { BidResult::Loss },
+        winner: last_auction_result.winner.clone(),
+        winning_bid: Uint128::from_str(&winning_bid)?,
+        proceeds: bid_attempt
+            .basket
+            .iter()
+            .filter(|asset| won && asset.denom == config.accepted_denom)
+            .cloned()
+            .collect(),
+        profit: Uint128::zero(),
+        loss: Uint128::zero(),
+        keeper_reward: Uint128::zero(),
+        index: GLOBAL.load(deps.storage)?.index,
+    };
+    record_round_result(deps.storage, &record)?;
+
     let mut response = Response::new()
         .add_attribute("method", "try_settle")
         .add_attribute("round", bid_attempt.round.to_string())
         .add_event(
             Event::new("bid_info")
                 .add_attribute("winner", &last_auction_result.winner)
-                .add_attribute("bid_amount", &last_auction_result.amount.unwrap().amount),
+                .add_attribute("bid_amount", &winning_bid),
         );
 
-    if env.contract.address.as_str() != &last_auction_result.winner {
+    if !won {
+        // a lost round distributes nothing, its locks expire without the callback
+        expire_locks_until(deps.storage, &env, bid_attempt.round)?;
+
         response = response
             .add_event(Event::new("bid_result").add_attribute("result", BidResult::Loss))
             .add_attribute("winning_bidder", "");
 
+        response =
+            response.add_submessage(create_process_queue_message(env.contract.address.as_str())?);
+
         //If we lose we may delete the rewards ?
         return Ok(response);
     }
@@ -287,8 +423,6 @@ pub fn try_settle(
         .add_event(Event::new("bid_result").add_attribute("result", BidResult::Win))
         .add_attribute("winning_bidder", bid_attempt.submitted_by.to_string());
 
-    let config = CONFIG.load(deps.storage)?;
-
     for asset in bid_attempt.basket.iter() {
         // skip accepted denom (inj)
         if &asset.denom == &config.accepted_denom {
@@ -321,6 +455,7 @@ pub fn try_settle(
         submsg.payload = to_json_binary(&SellAssetPayload {
             sell_type,
             coin: asset.to_owned(),
+            round: bid_attempt.round,
         })?;
 
         response = response
@@ -335,6 +470,10 @@ pub fn try_settle(
         &config,
     )?);
 
+    // queued deposits are processed after the profit distribution
+    response =
+        response.add_submessage(create_process_queue_message(env.contract.address.as_str())?);
+
     Ok(response)
 }
 
@@ -397,7 +536,28 @@ pub fn try_clear_current_bid(
 
     BID_ATTEMPT.remove(deps.storage);
 
+    // the round won't be settled, it is recorded as lost to the current highest bidder
+    let record = RoundRecord {
+        round: bid_attempt.round,
+        bid_amount: bid_attempt.amount,
+        keeper: bid_attempt.submitted_by.clone(),
+        basket: bid_attempt.basket.clone(),
+        result: BidResult::Loss,
+        winner: current_auction.highestBidder.clone(),
+        winning_bid: Uint128::from_str(&current_auction.highestBidAmount)?,
+        proceeds: vec![],
+        profit: Uint128::zero(),
+        loss: Uint128::zero(),
+        keeper_reward: Uint128::zero(),
+        index: GLOBAL.load(deps.storage)?.index,
+    };
+    record_round_result(deps.storage, &record)?;
+
+    // the locks ending at this round are already withdrawable, see check_unlocked
+    expire_locks_until(deps.storage, &env, bid_attempt.round)?;
+
     return Ok(Response::new()
+        .add_submessage(create_process_queue_message(env.contract.address.as_str())?)
         .add_attribute("method", "try_clear_current_bid")
         .add_attribute("round", bid_attempt.round.to_string()));
 }
@@ -0,0 +1,66 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::{BidStrategy, Config};
+use cosmwasm_std::Uint128;
+
+/* The bid is the minimum next bid of the auction, the strategy only decides whether it is
+worth placing. The expected profit is the basket value minus the estimated fees and the bid,
+of which the winning bidder takes its reward */
+
+pub fn validate_bid_strategy(bid_strategy: &BidStrategy) -> Result<(), ContractError> {
+    if bid_strategy.taker_fee_bps >= Uint128::new(10000)
+        || bid_strategy.max_supply_bps > Uint128::new(10000)
+    {
+        return Err(ContractError::InvalidBidStrategy {});
+    }
+
+    Ok(())
+}
+
+pub fn check_bid(
+    config: &Config,
+    bid: Uint128,
+    basket_value: Uint128,
+    total_supply: Uint128,
+) -> Result<(), ContractError> {
+    let bid_strategy = &config.bid_strategy;
+
+    if !bid_strategy.max_bid.is_zero() && bid > bid_strategy.max_bid {
+        return Err(ContractError::BidAboveMax {
+            bid,
+            max_bid: bid_strategy.max_bid,
+        });
+    }
+
+    if !bid_strategy.max_supply_bps.is_zero() {
+        let max_bid = total_supply.multiply_ratio(bid_strategy.max_supply_bps, 10000u128);
+        if bid > max_bid {
+            return Err(ContractError::BidAboveSupplyShare { bid, max_bid });
+        }
+    }
+
+    if !bid_strategy.min_margin_bps.is_zero() {
+        let expected_profit = expected_profit(config, bid, basket_value);
+        let required_profit = bid.multiply_ratio(bid_strategy.min_margin_bps, 10000u128);
+
+        if expected_profit < required_profit {
+            return Err(ContractError::BidMarginTooLow {
+                bid,
+                expected_profit,
+                required_profit,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Profit left to the depositors if the basket sells for its value
+fn expected_profit(config: &Config, bid: Uint128, basket_value: Uint128) -> Uint128 {
+    let value_after_fees = basket_value.multiply_ratio(
+        Uint128::new(10000) - config.bid_strategy.taker_fee_bps,
+        10000u128,
+    );
+    let profit = value_after_fees.saturating_sub(bid);
+
+    profit - profit.multiply_ratio(config.winning_bidder_reward_bps, 10000u128)
+}
@@ -4,8 +4,12 @@ use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper};
 use auction_dao::error::ContractError;
 use auction_dao::msg::CallbackMsg;
 
-use crate::lp::update_global_index;
-use crate::state::{CONFIG, GLOBAL, SETTLED_AMOUNT_TRANSIENT};
+use crate::lock::expire_locks;
+use crate::lp::{apply_loss, get_max_tokens, update_global_index};
+use crate::state::{CONFIG, GLOBAL, ROUND_HISTORY, ROUND_LOSSES, SETTLED_AMOUNT_TRANSIENT};
+use crate::stats::{record_round_settlement, record_supply};
+use crate::vault::compound_vault;
+use crate::vesting::{add_vesting_profit, load_global};
 
 pub fn callback(
     deps: DepsMut<InjectiveQueryWrapper>,
@@ -24,6 +28,7 @@ pub fn callback(
             SETTLED_AMOUNT_TRANSIENT.remove(deps.storage);
 
             let profit = received_from_basket_sell.saturating_sub(bid_amount);
+            let loss = bid_amount.saturating_sub(received_from_basket_sell);
             let config = CONFIG.load(deps.storage)?;
 
             let mut winning_reward =
@@ -42,23 +47,64 @@ pub fn callback(
                     received_from_basket_sell.to_string(),
                 )
                 .add_attribute("dao_profit", profit.to_string())
+                .add_attribute("loss", loss.to_string())
                 .add_attribute("reward", winning_reward.to_string());
 
             if winning_reward > Uint128::zero() {
                 response = response.add_message(CosmosMsg::Bank(BankMsg::Send {
                     to_address: bid_attempt.submitted_by.to_string(),
                     amount: vec![Coin {
-                        denom: config.accepted_denom,
+                        denom: config.accepted_denom.clone(),
                         amount: winning_reward,
                     }],
                 }));
             }
 
-            let mut global = GLOBAL.load(deps.storage)?;
-            global.profit_to_distribute += dao_profit;
-            update_global_index(&mut global);
+            // a failing valuation must not block the settlement, compounding is skipped instead
+            let max_tokens = get_max_tokens(deps.as_ref(), &config).unwrap_or_default();
+
+            // the profit of the previous rounds vested so far goes to the current supply
+            let now = env.block.time.seconds();
+            let mut global = load_global(deps.storage, &env)?;
+
+            // the depositors principal is reduced pro rata by the loss
+            if loss > Uint128::zero() {
+                let loss = apply_loss(&mut global, loss)?;
+                ROUND_LOSSES.save(deps.storage, bid_attempt.round, &loss)?;
+            }
+
+            expire_locks(deps.storage, &mut global, bid_attempt.round)?;
+
+            // manual swaps have no round
+            if bid_attempt.round > 0 {
+                global.last_settled_round = bid_attempt.round;
+            }
+
+            if config.reward_vesting_secs == 0 {
+                update_global_index(&mut global, dao_profit, max_tokens)?;
+            } else {
+                add_vesting_profit(
+                    &mut global,
+                    dao_profit,
+                    config.reward_vesting_secs,
+                    now,
+                    max_tokens,
+                );
+            }
+            compound_vault(deps.storage, &mut global)?;
+            // losses and compounding change the supply
+            record_supply(deps.storage, &global, now)?;
             GLOBAL.save(deps.storage, &global)?;
 
+            if let Some(mut record) = ROUND_HISTORY.may_load(deps.storage, bid_attempt.round)? {
+                record.profit = profit;
+                record.loss = loss;
+                record.keeper_reward = winning_reward;
+                record.index = global.index;
+                ROUND_HISTORY.save(deps.storage, bid_attempt.round, &record)?;
+                record_round_settlement(deps.storage, &record, received_from_basket_sell)?;
+            }
+
             Ok(response)
         }
     };
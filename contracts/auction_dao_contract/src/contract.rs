@@ -1,22 +1,38 @@
 use crate::admins::{delete_route, manual_swap, set_route};
 use crate::auction::{self};
-use crate::lp::{deposit, harvest, withdraw};
-use crate::state::{BID_ATTEMPT, BID_ATTEMPT_TRANSIENT, CONFIG, GLOBAL, SETTLED_AMOUNT_TRANSIENT};
+use crate::bid_strategy::validate_bid_strategy;
+use crate::lock::validate_lock_tiers;
+use crate::lp::{
+    claim_withdraw, credit_deposit, deposit, deposit_with_swap, harvest, request_withdraw,
+    set_auto_compound, transfer_position, withdraw, withdraw_as,
+};
+use crate::queue::process_queue;
+use crate::referral::validate_referral_reward_bps;
+use crate::shutdown::shutdown;
+use crate::state::{
+    BID_ATTEMPT, BID_ATTEMPT_TRANSIENT, CONFIG, GLOBAL, ROUND_HISTORY, SETTLED_AMOUNT_TRANSIENT,
+};
+use crate::vault::{create_receipt_denom, vault_deposit, vault_withdraw};
 use crate::{admins, callback::callback, queries};
 use auction_dao::error::ContractError;
 use auction_dao::msg::{
-    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SELL_ASSET_SUCCESS_REPLY_ID,
-    TRY_BID_SUCCESS_REPLY_ID,
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, DEPOSIT_SWAP_SUCCESS_REPLY_ID,
+    PROCESS_QUEUE_REPLY_ID, SELL_ASSET_SUCCESS_REPLY_ID, TRY_BID_SUCCESS_REPLY_ID,
+    WITHDRAW_SWAP_SUCCESS_REPLY_ID,
+};
+use auction_dao::state::{
+    Config, DepositSwapPayload, Global, SellAssetPayload, SellType, WithdrawSwapPayload,
 };
-use auction_dao::state::{Config, Global, SellAssetPayload, SellType};
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, Uint128,
+    entry_point, to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response,
+    Uint128,
 };
 use cosmwasm_std::{from_json, Decimal256, Uint256};
 use cw2::{get_contract_version, set_contract_version};
 use injective_cosmwasm::{
     get_default_subaccount_id_for_checked_address, InjectiveMsgWrapper, InjectiveQueryWrapper,
 };
+use injective_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
 use injective_std::types::injective::exchange::v1beta1 as Exchange;
 use prost::Message;
 use std::str::FromStr;
@@ -35,6 +51,10 @@ pub fn instantiate(
     cw_ownable::initialize_owner(deps.storage, deps.api, Some(&msg.admin))?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    validate_lock_tiers(&msg.lock_tiers)?;
+    validate_referral_reward_bps(msg.referral_reward_bps)?;
+    validate_bid_strategy(&msg.bid_strategy)?;
+
     GLOBAL.save(deps.storage, &Global::default())?;
     CONFIG.save(
         deps.storage,
@@ -49,6 +69,12 @@ pub fn instantiate(
                 &env.contract.address,
             ),
             winning_bidder_reward_bps: msg.winning_bidder_reward_bps,
+            lock_tiers: msg.lock_tiers,
+            referral_reward_bps: msg.referral_reward_bps,
+            shutdown: false,
+            reward_vesting_secs: msg.reward_vesting_secs,
+            bid_strategy: msg.bid_strategy,
+            valuation_policy: msg.valuation_policy,
         },
     )?;
 
@@ -67,19 +93,40 @@ pub fn execute(
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     match msg {
         ExecuteMsg::Callback(msg) => callback(deps, env, info, msg),
-        ExecuteMsg::Deposit {} => deposit(deps, info),
-        ExecuteMsg::Harvest {} => harvest(deps, info),
+        ExecuteMsg::Deposit {
+            recipient,
+            lock_rounds,
+            referrer,
+        } => deposit(deps, env, info, recipient, lock_rounds, referrer),
+        ExecuteMsg::DepositWithSwap { min_out } => deposit_with_swap(deps, env, info, min_out),
+        ExecuteMsg::Harvest {} => harvest(deps, env, info),
         ExecuteMsg::ManualExchangeSwap {
             amount,
             market_id,
             asset,
         } => manual_swap(deps, env, &info.sender, amount, &market_id, &asset),
-        ExecuteMsg::Withdraw { amount } => withdraw(deps, env, info, amount),
+        ExecuteMsg::Shutdown {} => shutdown(deps, &info.sender),
+        ExecuteMsg::Withdraw { amount, recipient } => withdraw(deps, env, info, amount, recipient),
+        ExecuteMsg::WithdrawAs {
+            amount,
+            denom,
+            min_out,
+        } => withdraw_as(deps, env, info, amount, denom, min_out),
+        ExecuteMsg::RequestWithdraw { amount } => request_withdraw(deps, env, info, amount),
+        ExecuteMsg::ClaimWithdraw {} => claim_withdraw(deps, env, info),
+        ExecuteMsg::TransferPosition { to, amount } => {
+            transfer_position(deps, env, info, to, amount)
+        }
+        ExecuteMsg::SetAutoCompound { enabled } => set_auto_compound(deps, env, info, enabled),
+        ExecuteMsg::VaultDeposit {} => vault_deposit(deps, env, info),
+        ExecuteMsg::VaultWithdraw {} => vault_withdraw(deps, env, info),
+        ExecuteMsg::CreateReceiptDenom {} => create_receipt_denom(deps, env, info),
         ExecuteMsg::TryBid { round } => auction::try_bid(deps, env, info, round),
         ExecuteMsg::TrySettle {} => auction::try_settle(deps, env, &info.sender),
         ExecuteMsg::TryClearCurrentBid {} => {
             auction::try_clear_current_bid(deps, env, &info.sender)
         }
+        ExecuteMsg::ProcessQueue { limit } => process_queue(deps, env, limit),
         ExecuteMsg::UpdateConfig { new_config } => {
             admins::update_config(deps, &info.sender, new_config)
         }
@@ -98,7 +145,7 @@ pub fn execute(
 #[entry_point]
 pub fn query(
     deps: Deps<InjectiveQueryWrapper>,
-    _env: Env,
+    env: Env,
     msg: QueryMsg,
 ) -> Result<Binary, ContractError> {
     match msg {
@@ -110,7 +157,7 @@ pub fn query(
             let config = CONFIG.load(deps.storage)?;
             Ok(to_json_binary(&config)?)
         }
-        QueryMsg::User { address } => queries::query_user(deps, address),
+        QueryMsg::User { address } => queries::query_user(deps, env, address),
         QueryMsg::CurrentAuctionBasket {} => queries::query_current_auction_basket(deps),
         QueryMsg::ExchangeSimulateSwap {
             amount,
@@ -124,13 +171,48 @@ pub fn query(
             queries::query_current_auction_value_using_router(deps)
         }
         QueryMsg::MaxAllowedTokensToDeposit {} => queries::query_max_tokens(deps),
+        QueryMsg::BasketValuation {} => queries::query_basket_valuation(deps),
+        QueryMsg::BidCapacity {} => queries::query_bid_capacity(deps, env),
+        QueryMsg::Vault {} => queries::query_vault(deps, env),
+        QueryMsg::UserRewards { address } => queries::query_user_rewards(deps, env, address),
+        QueryMsg::QueuedActions { address } => queries::query_queued_actions(deps, address),
+        QueryMsg::WithdrawRequest { address } => queries::query_withdraw_request(deps, address),
+        QueryMsg::RoundLoss { round } => queries::query_round_loss(deps, round),
+        QueryMsg::Eligibility { address } => queries::query_eligibility(deps, env, address),
+        QueryMsg::Referrals {
+            referrer,
+            start_after,
+            limit,
+        } => queries::query_referrals(deps, referrer, start_after, limit),
+        QueryMsg::Users { start_after, limit } => {
+            queries::query_users(deps, env, start_after, limit)
+        }
+        QueryMsg::TotalPendingRewards {} => queries::query_total_pending_rewards(deps, env),
+        QueryMsg::Solvency {} => queries::query_solvency(deps, env),
+        QueryMsg::ShutdownStatus {} => queries::query_shutdown_status(deps, env),
+        QueryMsg::Vesting {} => queries::query_vesting(deps, env),
+        QueryMsg::UserPerformance { address } => {
+            queries::query_user_performance(deps, env, address)
+        }
+        QueryMsg::GlobalPerformance {} => queries::query_global_performance(deps, env),
+        QueryMsg::AccumulatedDust {} => {
+            let global = GLOBAL.load(deps.storage)?;
+            Ok(to_json_binary(&global.accumulated_dust)?)
+        }
+        QueryMsg::Stats {} => queries::query_stats(deps),
+        QueryMsg::Rounds { start_after, limit } => queries::query_rounds(deps, start_after, limit),
+        QueryMsg::UserHistory {
+            address,
+            start_after,
+            limit,
+        } => queries::query_user_history(deps, address, start_after, limit),
     }
 }
 
 #[entry_point]
 pub fn reply(
     deps: DepsMut<InjectiveQueryWrapper>,
-    _env: Env,
+    env: Env,
     msg: cosmwasm_std::Reply,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     match msg.id {
@@ -143,60 +225,27 @@ pub fn reply(
             return Ok(Response::new());
         }
         SELL_ASSET_SUCCESS_REPLY_ID => {
-            let binding = msg
-                .result
-                .into_result()
-                .map_err(ContractError::SubMsgFailure)?;
-
-            let first_messsage = binding.msg_responses.first();
-
-            let order_response = Exchange::MsgCreateSpotMarketOrderResponse::decode(
-                first_messsage
-                    .ok_or_else(|| {
-                        ContractError::SubMsgFailure("No message responses found".to_string())
-                    })?
-                    .value
-                    .as_slice(),
-            )
-            .map_err(|err| ContractError::ReplyParseFailure {
-                id: msg.id,
-                err: err.to_string(),
-            })?;
-
-            let trade_data = order_response
-                .results
-                .ok_or_else(|| ContractError::SubMsgFailure("No trade data".to_owned()))
-                .unwrap();
-
             if msg.payload.is_empty() {
                 return Err(ContractError::EmptySellAssetPayload {});
             }
 
-            let payload = from_json::<SellAssetPayload>(msg.payload)?;
-
-            // deps.api.debug(&format!("Trade data: {:?}", trade_data));
-
-            let received = match payload.sell_type {
-                SellType::Quote => {
-                    Decimal256::from_atomics(Uint256::from_str(&trade_data.quantity)?, 18)?
-                        .to_uint_floor()
-                }
-                SellType::Base => {
-                    let q = Decimal256::from_atomics(Uint256::from_str(&trade_data.quantity)?, 18)?;
-                    let p = Decimal256::from_atomics(Uint256::from_str(&trade_data.price)?, 18)?;
-                    let fee = Decimal256::from_atomics(Uint256::from_str(&trade_data.fee)?, 18)?;
-
-                    (q * p - fee).to_uint_floor()
-                }
-            };
-
-            let received_u128 = Uint128::from_str(received.to_string().as_str())?;
+            let payload = from_json::<SellAssetPayload>(&msg.payload)?;
+            let received_u128 = parse_received_amount(msg, &payload.sell_type)?;
 
             SETTLED_AMOUNT_TRANSIENT
                 .update(deps.storage, |amount| -> Result<_, ContractError> {
                     Ok(amount + received_u128)
                 })?;
 
+            // manual swaps have no round
+            if let Some(mut record) = ROUND_HISTORY.may_load(deps.storage, payload.round)? {
+                record.proceeds.push(ProtoCoin {
+                    denom: payload.coin.denom.clone(),
+                    amount: received_u128.to_string(),
+                });
+                ROUND_HISTORY.save(deps.storage, payload.round, &record)?;
+            }
+
             let response = Response::new().add_attribute(
                 format!("received_inj::{}", payload.coin.denom),
                 received_u128.to_string(),
@@ -204,10 +253,154 @@ pub fn reply(
 
             return Ok(response);
         }
+        DEPOSIT_SWAP_SUCCESS_REPLY_ID => {
+            if msg.payload.is_empty() {
+                return Err(ContractError::EmptySellAssetPayload {});
+            }
+
+            let payload = from_json::<DepositSwapPayload>(&msg.payload)?;
+            let received = parse_received_amount(msg, &payload.sell_type)?;
+
+            if received < payload.min_out {
+                return Err(ContractError::SwapOutputTooLow(received, payload.min_out));
+            }
+
+            let unspent = deps
+                .querier
+                .query_balance(&env.contract.address, &payload.coin.denom)?
+                .amount
+                .saturating_sub(payload.held);
+
+            let config = CONFIG.load(deps.storage)?;
+            let mut response = credit_deposit(
+                deps,
+                &env,
+                &config,
+                &payload.sender,
+                &payload.sender,
+                received,
+                None,
+                None,
+            )?;
+
+            if unspent > Uint128::zero() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: payload.sender.to_string(),
+                    amount: vec![Coin {
+                        denom: payload.coin.denom.clone(),
+                        amount: unspent,
+                    }],
+                });
+            }
+
+            return Ok(response
+                .add_attribute(
+                    format!("received_inj::{}", payload.coin.denom),
+                    received.to_string(),
+                )
+                .add_attribute(format!("unspent::{}", payload.coin.denom), unspent));
+        }
+        WITHDRAW_SWAP_SUCCESS_REPLY_ID => {
+            if msg.payload.is_empty() {
+                return Err(ContractError::EmptySellAssetPayload {});
+            }
+
+            let payload = from_json::<WithdrawSwapPayload>(&msg.payload)?;
+            let received = parse_received_amount(msg, &payload.sell_type)?;
+
+            if received < payload.min_out {
+                return Err(ContractError::SwapOutputTooLow(received, payload.min_out));
+            }
+
+            let config = CONFIG.load(deps.storage)?;
+            let unspent = deps
+                .querier
+                .query_balance(&env.contract.address, &config.accepted_denom)?
+                .amount
+                .saturating_sub(payload.held);
+
+            let mut response = Response::new()
+                .add_attribute(format!("received::{}", payload.denom), received.to_string())
+                .add_attribute(format!("unspent::{}", config.accepted_denom), unspent);
+
+            if received > Uint128::zero() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: payload.recipient.to_string(),
+                    amount: vec![Coin {
+                        denom: payload.denom,
+                        amount: received,
+                    }],
+                });
+            }
+
+            if unspent > Uint128::zero() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: payload.recipient.to_string(),
+                    amount: vec![Coin {
+                        denom: config.accepted_denom,
+                        amount: unspent,
+                    }],
+                });
+            }
+
+            return Ok(response);
+        }
+        PROCESS_QUEUE_REPLY_ID => {
+            // only called on error, the queue stays in place and can be processed later
+            let err = msg.result.into_result().err().unwrap_or_default();
+
+            return Ok(Response::new().add_attribute("process_queue_error", err));
+        }
         _ => Err(ContractError::InvalidReply(msg.id)),
     }
 }
 
+// amount received from an atomic spot order
+fn parse_received_amount(
+    msg: cosmwasm_std::Reply,
+    sell_type: &SellType,
+) -> Result<Uint128, ContractError> {
+    let binding = msg
+        .result
+        .into_result()
+        .map_err(ContractError::SubMsgFailure)?;
+
+    let first_messsage = binding.msg_responses.first();
+
+    let order_response = Exchange::MsgCreateSpotMarketOrderResponse::decode(
+        first_messsage
+            .ok_or_else(|| ContractError::SubMsgFailure("No message responses found".to_string()))?
+            .value
+            .as_slice(),
+    )
+    .map_err(|err| ContractError::ReplyParseFailure {
+        id: msg.id,
+        err: err.to_string(),
+    })?;
+
+    let trade_data = order_response
+        .results
+        .ok_or_else(|| ContractError::SubMsgFailure("No trade data".to_owned()))
+        .unwrap();
+
+    // deps.api.debug(&format!("Trade data: {:?}", trade_data));
+
+    let received = match sell_type {
+        SellType::Quote => {
+            Decimal256::from_atomics(Uint256::from_str(&trade_data.quantity)?, 18)?.to_uint_floor()
+        }
+        SellType::Base => {
+            let q = Decimal256::from_atomics(Uint256::from_str(&trade_data.quantity)?, 18)?;
+            let p = Decimal256::from_atomics(Uint256::from_str(&trade_data.price)?, 18)?;
+            let fee = Decimal256::from_atomics(Uint256::from_str(&trade_data.fee)?, 18)?;
+
+            (q * p - fee).to_uint_floor()
+        }
+    };
+
+    Ok(Uint128::from_str(received.to_string().as_str())?)
+}
+
 #[entry_point]
 pub fn migrate(
     deps: DepsMut<InjectiveQueryWrapper>,
@@ -0,0 +1,138 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::{Global, HistoryAction, HistoryEntry, UserAccount};
+use cosmwasm_std::{Decimal256, Order, Storage, Uint128};
+
+use crate::{
+    state::{ROUND_HISTORY, USER_ACCOUNTS, USER_HISTORY, USER_HISTORY_LENGTHS},
+    stats::record_stats,
+};
+
+/* The rewards of a user are only computed when the account is updated, so the accruals
+are recorded when the account is saved, as the difference with the stored account.
+The difference is split into one accrual entry per round, see record_accruals */
+
+pub fn record_history(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    action: HistoryAction,
+    amount: Uint128,
+    global: &Global,
+) -> Result<(), ContractError> {
+    record_round_history(
+        storage,
+        user_addr,
+        action,
+        amount,
+        global.last_settled_round,
+    )
+}
+
+fn record_round_history(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    action: HistoryAction,
+    amount: Uint128,
+    last_settled_round: u64,
+) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+
+    record_stats(storage, user_addr, &action, amount)?;
+
+    let id = USER_HISTORY_LENGTHS
+        .may_load(storage, user_addr)?
+        .unwrap_or_default()
+        + 1;
+
+    USER_HISTORY.save(
+        storage,
+        (user_addr, id),
+        &HistoryEntry {
+            action,
+            amount,
+            last_settled_round,
+        },
+    )?;
+    USER_HISTORY_LENGTHS.save(storage, user_addr, &id)?;
+
+    Ok(())
+}
+
+// Must run before the updated account is stored
+pub fn record_reward_changes(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    user_account: &UserAccount,
+    global: &Global,
+) -> Result<(), ContractError> {
+    let stored_account = USER_ACCOUNTS
+        .may_load(storage, user_addr)?
+        .unwrap_or_default();
+
+    let accrued = total_earned(user_account).saturating_sub(total_earned(&stored_account));
+    let loss = user_account
+        .total_loss
+        .saturating_sub(stored_account.total_loss);
+
+    record_accruals(storage, user_addr, accrued, stored_account.index, global)?;
+    record_history(storage, user_addr, HistoryAction::Loss, loss, global)
+}
+
+/* The accrual is split over the rounds settled since the stored account in proportion
+to the index each of them added, ROUND_HISTORY keeps the index after every round.
+The profit vested or swapped manually between two rounds counts with the later one */
+fn record_accruals(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    accrued: Uint128,
+    stored_index: Decimal256,
+    global: &Global,
+) -> Result<(), ContractError> {
+    if accrued.is_zero() {
+        return Ok(());
+    }
+
+    // the index only grows, so the rounds are walked back until the stored one
+    let mut round_indexes = vec![];
+    for item in ROUND_HISTORY.range(storage, None, None, Order::Descending) {
+        let (round, record) = item?;
+        if record.index <= stored_index {
+            break;
+        }
+        round_indexes.push((round, record.index));
+    }
+    round_indexes.reverse();
+
+    let index_growth = global.index.saturating_sub(stored_index);
+    let accrued_decimal = Decimal256::from_atomics(accrued.u128(), 0)?;
+
+    let mut accruals = vec![];
+    let mut previous_index = stored_index;
+    let mut recorded = Uint128::zero();
+    for (round, index) in round_indexes {
+        let amount = Uint128::try_from(
+            (accrued_decimal * (index - previous_index) / index_growth).to_uint_floor(),
+        )?;
+        accruals.push((round, amount));
+        previous_index = index;
+        recorded += amount;
+    }
+
+    // the profit distributed after the last round, or the rounding left by the split
+    let rest = accrued - recorded;
+    match accruals.last_mut() {
+        Some((_, amount)) if previous_index >= global.index => *amount += rest,
+        _ => accruals.push((global.last_settled_round, rest)),
+    }
+
+    for (round, amount) in accruals {
+        record_round_history(storage, user_addr, HistoryAction::Accrual, amount, round)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn total_earned(user_account: &UserAccount) -> Uint128 {
+    user_account.pending_reward + user_account.total_harvested + user_account.total_compounded
+}
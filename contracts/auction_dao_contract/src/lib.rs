@@ -1,10 +1,19 @@
 pub mod admins;
 pub mod auction;
+pub mod bid_strategy;
 pub mod callback;
 pub mod contract;
 pub mod exchange;
 pub mod fixed_types;
+pub mod history;
+pub mod lock;
 pub mod lp;
 pub mod queries;
+pub mod queue;
+pub mod referral;
 pub mod router;
+pub mod shutdown;
 pub mod state;
+pub mod stats;
+pub mod vault;
+pub mod vesting;
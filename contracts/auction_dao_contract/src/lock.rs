@@ -0,0 +1,203 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::{Config, Global, LockTier, UserAccount};
+use cosmwasm_std::{Decimal256, Deps, Env, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::Bound;
+use injective_cosmwasm::InjectiveQueryWrapper;
+
+use crate::{
+    auction::get_current_auction,
+    state::{EXPIRED_LOCK_INDEXES, GLOBAL, LOCK_EXPIRIES},
+    vesting::load_global,
+};
+
+/* A locked deposit earns with an extra weight of locked * (multiplier - 1), the lock_bonus.
+The bonus of all the locks ending at a round is kept in LOCK_EXPIRIES and removed from
+global.total_lock_bonus before the profit of that round is distributed. Rounds that are
+lost, cleared or skipped distribute nothing, their locks expire when the round is
+settled or cleared, or when the next bid is placed, so they stop earning vested profit */
+
+pub fn validate_lock_tiers(lock_tiers: &[LockTier]) -> Result<(), ContractError> {
+    for tier in lock_tiers.iter() {
+        if tier.rounds == 0 || tier.multiplier_bps < Uint128::new(10000) {
+            return Err(ContractError::InvalidLockTier {});
+        }
+    }
+
+    Ok(())
+}
+
+// The locked part of a position can't leave before the lock ends, or until a shutdown
+pub fn check_unlocked(
+    deps: Deps<InjectiveQueryWrapper>,
+    config: &Config,
+    user_account: &UserAccount,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if config.shutdown || user_account.locked.is_zero() {
+        return Ok(());
+    }
+
+    let current_auction = get_current_auction(deps)?;
+
+    if user_account.lock_until_round > current_auction.auctionRound
+        && user_account.deposited.saturating_sub(user_account.locked) < amount
+    {
+        return Err(ContractError::PositionLocked(user_account.lock_until_round));
+    }
+
+    Ok(())
+}
+
+// The user reward must be up to date and the amount already added to deposited
+pub fn lock_deposit(
+    storage: &mut dyn Storage,
+    config: &Config,
+    user_account: &mut UserAccount,
+    global: &mut Global,
+    amount: Uint128,
+    rounds: u64,
+    current_round: u64,
+) -> Result<(), ContractError> {
+    let tier = config
+        .lock_tiers
+        .iter()
+        .find(|tier| tier.rounds == rounds)
+        .ok_or(ContractError::InvalidLockTier {})?;
+
+    if user_account.auto_compound {
+        return Err(ContractError::LockWithAutoCompound {});
+    }
+
+    let lock_active = user_account.lock_until_round > current_round;
+    if lock_active
+        && !user_account.locked.is_zero()
+        && user_account.lock_multiplier_bps != tier.multiplier_bps
+    {
+        return Err(ContractError::LockTierMismatch {});
+    }
+
+    // the current bonus moves to the new expiry round
+    remove_lock_bonus(storage, user_account, global)?;
+
+    let mut lock_until_round = current_round + rounds;
+    let mut locked = amount;
+    if lock_active {
+        lock_until_round = lock_until_round.max(user_account.lock_until_round);
+        locked += user_account.locked;
+    }
+
+    user_account.locked = locked;
+    user_account.lock_bonus = locked.multiply_ratio(
+        tier.multiplier_bps - Uint128::new(10000),
+        Uint128::new(10000),
+    );
+    user_account.lock_multiplier_bps = tier.multiplier_bps;
+    user_account.lock_until_round = lock_until_round;
+
+    let normalized_bonus = normalize(user_account.lock_bonus, global.principal_factor)?;
+    let expiring_bonus = LOCK_EXPIRIES
+        .may_load(storage, lock_until_round)?
+        .unwrap_or_default();
+    LOCK_EXPIRIES.save(
+        storage,
+        lock_until_round,
+        &(expiring_bonus + normalized_bonus),
+    )?;
+    global.total_lock_bonus += user_account.lock_bonus;
+
+    Ok(())
+}
+
+/* After a shutdown the locks end when the position moves, with their bonus. Otherwise the
+bonus would keep earning a share of the profit left, and the account, once emptied */
+pub fn release_lock_after_shutdown(
+    storage: &mut dyn Storage,
+    config: &Config,
+    user_account: &mut UserAccount,
+    global: &mut Global,
+) -> Result<(), ContractError> {
+    if !config.shutdown || user_account.locked.is_zero() {
+        return Ok(());
+    }
+
+    remove_lock_bonus(storage, user_account, global)?;
+    user_account.locked = Uint128::zero();
+
+    Ok(())
+}
+
+fn remove_lock_bonus(
+    storage: &mut dyn Storage,
+    user_account: &mut UserAccount,
+    global: &mut Global,
+) -> Result<(), ContractError> {
+    if user_account.lock_bonus.is_zero() {
+        return Ok(());
+    }
+
+    let normalized_bonus = normalize(user_account.lock_bonus, global.principal_factor)?;
+    if let Some(expiring_bonus) = LOCK_EXPIRIES.may_load(storage, user_account.lock_until_round)? {
+        let expiring_bonus = expiring_bonus.saturating_sub(normalized_bonus);
+        if expiring_bonus.is_zero() {
+            LOCK_EXPIRIES.remove(storage, user_account.lock_until_round);
+        } else {
+            LOCK_EXPIRIES.save(storage, user_account.lock_until_round, &expiring_bonus)?;
+        }
+    }
+
+    global.total_lock_bonus = global
+        .total_lock_bonus
+        .saturating_sub(user_account.lock_bonus);
+    user_account.lock_bonus = Uint128::zero();
+
+    Ok(())
+}
+
+// Removes the bonus of the locks ending until the given round included
+pub fn expire_locks(
+    storage: &mut dyn Storage,
+    global: &mut Global,
+    round: u64,
+) -> Result<(), ContractError> {
+    let expiries = LOCK_EXPIRIES
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(round)),
+            Order::Ascending,
+        )
+        .collect::<StdResult<Vec<(u64, Uint128)>>>()?;
+
+    for (expiry_round, normalized_bonus) in expiries {
+        let bonus = Uint128::try_from(
+            (Decimal256::from_atomics(normalized_bonus.u128(), 0)? * global.principal_factor)
+                .to_uint_floor(),
+        )?;
+
+        global.total_lock_bonus = global.total_lock_bonus.saturating_sub(bonus);
+        LOCK_EXPIRIES.remove(storage, expiry_round);
+        EXPIRED_LOCK_INDEXES.save(storage, expiry_round, &global.index)?;
+    }
+
+    Ok(())
+}
+
+// Expires the locks ending until the round included, after the profit vested so far
+pub fn expire_locks_until(
+    storage: &mut dyn Storage,
+    env: &Env,
+    round: u64,
+) -> Result<(), ContractError> {
+    let mut global = load_global(storage, env)?;
+    expire_locks(storage, &mut global, round)?;
+    GLOBAL.save(storage, &global)?;
+
+    Ok(())
+}
+
+// bonus in units deposited before any loss, so the buckets don't need to follow the losses
+fn normalize(bonus: Uint128, principal_factor: Decimal256) -> Result<Uint128, ContractError> {
+    Ok(Uint128::try_from(
+        (Decimal256::from_atomics(bonus.u128(), 0)? / principal_factor).to_uint_floor(),
+    )?)
+}
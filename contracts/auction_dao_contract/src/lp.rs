@@ -1,15 +1,31 @@
-use auction_dao::state::{Config, Global, UserAccount};
+use auction_dao::msg::{DEPOSIT_SWAP_SUCCESS_REPLY_ID, WITHDRAW_SWAP_SUCCESS_REPLY_ID};
+use auction_dao::state::{
+    Config, DepositSwapPayload, Global, HistoryAction, UserAccount, WithdrawRequest,
+    WithdrawSwapPayload,
+};
 use cosmwasm_std::{
-    BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env, MessageInfo, Response, Timestamp,
-    Uint128,
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env, MessageInfo,
+    Response, Storage, SubMsg, Timestamp, Uint128,
 };
 use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper};
+use injective_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
 
 use auction_dao::error::ContractError;
 
 use crate::{
-    auction::{get_current_auction, get_current_auction_value_using_exchange},
-    state::{BID_ATTEMPT, CONFIG, GLOBAL, USER_ACCOUNTS},
+    auction::{get_basket_value, get_current_auction},
+    exchange::swap,
+    history::{record_history, record_reward_changes},
+    lock::{check_unlocked, lock_deposit, release_lock_after_shutdown},
+    queue::{queue_deposit, queue_withdraw},
+    referral::{credit_referral_fee, set_referrer, take_referral_fee},
+    shutdown::{apply_exit_ratio, ensure_not_shutdown, exit_ratio},
+    state::{
+        read_swap_route, BID_ATTEMPT, CONFIG, EXPIRED_LOCK_INDEXES, GLOBAL, REFERRER_REWARDS,
+        USER_ACCOUNTS, WITHDRAW_REQUESTS,
+    },
+    stats::{record_balance, record_supply},
+    vesting::load_global,
 };
 
 /*   Dynamic max_tokens based on current basket value
@@ -20,54 +36,255 @@ pub fn get_max_tokens(
     deps: Deps<InjectiveQueryWrapper>,
     config: &Config,
 ) -> Result<Uint128, ContractError> {
-    let basket_value = get_current_auction_value_using_exchange(deps)?;
+    let basket_value = get_basket_value(deps, config)?;
     let max_tokens =
         basket_value.multiply_ratio(config.max_inj_offset_bps, Uint128::from(10000u128));
 
     return Ok(max_tokens);
 }
 
+// We allow withdraws until the auctionClosingTime - withdraw_time_buffer_secs, or anytime after a shutdown
+pub fn check_withdraw_time(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+) -> Result<(), ContractError> {
+    if config.shutdown {
+        return Ok(());
+    }
+
+    let current_auction = get_current_auction(deps)?;
+    let end_auction_ts =
+        Timestamp::from_seconds(u64::try_from(current_auction.auctionClosingTime)?);
+
+    if env.block.time > end_auction_ts.minus_seconds(config.withdraw_time_buffer_secs) {
+        let buffer_in_min = config.withdraw_time_buffer_secs / 60;
+        let end_time_in_min = end_auction_ts
+            .minus_seconds(env.block.time.seconds())
+            .seconds()
+            / 60;
+        return Err(ContractError::NotInWithdrawTime(
+            buffer_in_min,
+            end_time_in_min,
+        ));
+    }
+
+    Ok(())
+}
+
 pub fn deposit(
     deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
     info: MessageInfo,
+    recipient: Option<String>,
+    lock_rounds: Option<u64>,
+    referrer: Option<String>,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     let config = CONFIG.load(deps.storage)?;
+    ensure_not_shutdown(&config)?;
+
     if info.funds.len() != 1 || info.funds[0].denom != config.accepted_denom {
         return Err(ContractError::InvalidDenom {});
     }
 
-    let max_tokens = get_max_tokens(deps.as_ref(), &config)?;
+    // the deposit is credited to the recipient when one is given
+    let beneficiary = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
 
-    let amount = info.funds[0].amount;
-    let mut global = GLOBAL.load(deps.storage)?;
+    credit_deposit(
+        deps,
+        &env,
+        &config,
+        &info.sender,
+        &beneficiary,
+        info.funds[0].amount,
+        lock_rounds,
+        referrer,
+    )
+}
 
-    if global.total_supply + amount > max_tokens {
-        return Err(ContractError::MaxTokensExceeded {});
+/* The deposited amount is swapped to the accepted denom with an atomic order first,
+it is credited to the sender in the reply once the received amount is known,
+together with a refund of the deposited amount the order didn't spend */
+pub fn deposit_with_swap(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    min_out: Uint128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_not_shutdown(&config)?;
+
+    if info.funds.len() != 1 || info.funds[0].denom == config.accepted_denom {
+        return Err(ContractError::InvalidDenom {});
     }
 
-    // Check if there is a bid, if so, we can't deposit
-    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
-        return Err(ContractError::ActiveBid {});
+    let coin = info.funds[0].clone();
+    let route = read_swap_route(deps.as_ref(), &coin.denom, &config.accepted_denom)?;
+
+    // the order is rounded to the market ticks, what it doesn't spend goes back to the sender
+    let held = deps
+        .querier
+        .query_balance(&env.contract.address, &coin.denom)?
+        .amount
+        - coin.amount;
+
+    let (msg, sell_type) = swap(
+        deps.as_ref(),
+        &env.contract.address,
+        coin.amount,
+        route.market_id.as_str(),
+        &coin.denom,
+    )?;
+
+    let mut submsg = SubMsg::reply_on_success(msg, DEPOSIT_SWAP_SUCCESS_REPLY_ID);
+    submsg.payload = to_json_binary(&DepositSwapPayload {
+        sender: info.sender.clone(),
+        coin: ProtoCoin {
+            denom: coin.denom.clone(),
+            amount: coin.amount.to_string(),
+        },
+        sell_type,
+        min_out,
+        held,
+    })?;
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("method", "deposit_with_swap")
+        .add_attribute("sender", info.sender)
+        .add_attribute(format!("swap_out::{}", coin.denom), coin.amount)
+        .add_attribute("market_id", route.market_id.as_str()))
+}
+
+// Credits an amount of the accepted denom held by the contract, the part above the max tokens is refunded to the sender
+pub(crate) fn credit_deposit(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+    sender: &Addr,
+    beneficiary: &Addr,
+    deposited: Uint128,
+    lock_rounds: Option<u64>,
+    referrer: Option<String>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let mut global = load_global(deps.storage, env)?;
+    let (amount, refunded, msgs) =
+        accept_deposit(deps.as_ref(), config, &global, sender, deposited)?;
+
+    // a deposit for someone else would extend the lock of the recipient whole position
+    if lock_rounds.is_some() && beneficiary != sender {
+        return Err(ContractError::LockForRecipient {});
     }
 
-    let user_addr = info.sender.as_str();
+    // a deposit for someone else would give the referrer a share of the recipient rewards
+    let referrer = match referrer {
+        Some(_) if beneficiary != sender => return Err(ContractError::InvalidReferrer {}),
+        Some(referrer) => Some(deps.api.addr_validate(&referrer)?),
+        None => None,
+    };
+
+    let user_addr = beneficiary.as_str();
 
     let mut user_account = USER_ACCOUNTS
         .may_load(deps.storage, user_addr)
         .unwrap()
         .unwrap_or_default();
 
-    update_user_reward(&mut user_account, &global.index)?;
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+    if let Some(referrer) = &referrer {
+        set_referrer(deps.storage, beneficiary, &mut user_account, referrer)?;
+    }
+
+    // Check if there is a bid, if so, the deposit waits in the queue
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        // locks start with the next round
+        if lock_rounds.is_some() {
+            return Err(ContractError::ActiveBid {});
+        }
+
+        /* the account is kept even without deposit, so the referrer applies to the queued one.
+        A compounding account is saved too, the referral fee taken from it changed the global */
+        if referrer.is_some() || !user_account.deposited.is_zero() {
+            record_reward_changes(deps.storage, user_addr, &user_account, &global)?;
+            credit_referral_fee(deps.storage, user_addr, &mut user_account)?;
+            USER_ACCOUNTS.save(deps.storage, user_addr, &user_account)?;
+        }
+
+        let response = queue_deposit(deps, sender, beneficiary, amount, global)?;
+        return Ok(response
+            .add_messages(msgs)
+            .add_attribute("accepted", amount)
+            .add_attribute("refunded", refunded));
+    }
 
     increase_supply(&mut user_account, &mut global, &amount);
 
-    USER_ACCOUNTS.save(deps.storage, user_addr, &user_account)?;
+    if let Some(rounds) = lock_rounds {
+        let current_auction = get_current_auction(deps.as_ref())?;
+        lock_deposit(
+            deps.storage,
+            config,
+            &mut user_account,
+            &mut global,
+            amount,
+            rounds,
+            current_auction.auctionRound,
+        )?;
+    }
+
+    save_user_account(deps.storage, user_addr, &mut user_account, &global, env)?;
+    record_history(
+        deps.storage,
+        user_addr,
+        HistoryAction::Deposit,
+        amount,
+        &global,
+    )?;
     GLOBAL.save(deps.storage, &global)?;
 
     Ok(Response::new()
+        .add_messages(msgs)
         .add_attribute("method", "deposit")
-        .add_attribute("owner", user_addr)
-        .add_attribute("amount", amount))
+        .add_attribute("sender", sender)
+        .add_attribute("beneficiary", user_addr)
+        .add_attribute("accepted", amount)
+        .add_attribute("refunded", refunded))
+}
+
+// Only the amount fitting under the max tokens is accepted, the rest is refunded to the sender
+pub(crate) fn accept_deposit(
+    deps: Deps<InjectiveQueryWrapper>,
+    config: &Config,
+    global: &Global,
+    sender: &Addr,
+    deposited: Uint128,
+) -> Result<(Uint128, Uint128, Vec<CosmosMsg<InjectiveMsgWrapper>>), ContractError> {
+    let max_tokens = get_max_tokens(deps, config)?;
+
+    let headroom = max_tokens.saturating_sub(global.total_supply + global.queued_deposits);
+    if headroom.is_zero() {
+        return Err(ContractError::MaxTokensExceeded {});
+    }
+
+    let amount = deposited.min(headroom);
+    let refunded = deposited - amount;
+
+    let mut msgs: Vec<CosmosMsg<_>> = vec![];
+    if refunded > Uint128::zero() {
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: sender.to_string(),
+            amount: vec![Coin {
+                denom: config.accepted_denom.clone(),
+                amount: refunded,
+            }],
+        }));
+    }
+
+    Ok((amount, refunded, msgs))
 }
 
 pub fn withdraw(
@@ -75,78 +292,253 @@ pub fn withdraw(
     env: Env,
     info: MessageInfo,
     amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    // the withdrawn amount is sent to the recipient when one is given
+    let beneficiary = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
+
+    // Check if there is a bid, if so, the withdraw waits in the queue
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        return queue_withdraw(deps, &env, &info.sender, &beneficiary, amount);
+    }
+
+    let user_addr = info.sender.as_str();
+    let config = CONFIG.load(deps.storage)?;
+
+    let (amount_with_reward, payout) = remove_position(deps, &env, &config, user_addr, amount)?;
+
+    let mut msgs: Vec<CosmosMsg<_>> = vec![];
+    if payout > Uint128::zero() {
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: beneficiary.to_string(),
+            amount: vec![Coin {
+                denom: config.accepted_denom.clone(),
+                amount: payout,
+            }],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("method", "withdraw")
+        .add_attribute("sender", user_addr)
+        .add_attribute("beneficiary", beneficiary)
+        .add_attribute("amount", amount)
+        .add_attribute("rewards", amount_with_reward - amount)
+        .add_attribute("payout", payout))
+}
+
+/* The withdrawn amount and rewards are sold for the given denom with an atomic order,
+the received amount is sent to the user in the reply. The swap can't wait in the queue,
+so this is not allowed during an active bid */
+pub fn withdraw_as(
+    mut deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    denom: String,
+    min_out: Uint128,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
-    // Check if there is a bid
     if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
         return Err(ContractError::ActiveBid {});
     }
 
+    let config = CONFIG.load(deps.storage)?;
+    if denom == config.accepted_denom {
+        return Err(ContractError::InvalidDenom {});
+    }
+
+    let route = read_swap_route(deps.as_ref(), &config.accepted_denom, &denom)?;
+
     let user_addr = info.sender.as_str();
+    let (amount_with_reward, payout) =
+        remove_position(deps.branch(), &env, &config, user_addr, amount)?;
+
+    // the order is rounded to the market ticks, what it doesn't spend is sent back as well
+    let held = deps
+        .querier
+        .query_balance(&env.contract.address, &config.accepted_denom)?
+        .amount
+        - payout;
+
+    let (msg, sell_type) = swap(
+        deps.as_ref(),
+        &env.contract.address,
+        payout,
+        route.market_id.as_str(),
+        &config.accepted_denom,
+    )?;
 
+    let mut submsg = SubMsg::reply_on_success(msg, WITHDRAW_SWAP_SUCCESS_REPLY_ID);
+    submsg.payload = to_json_binary(&WithdrawSwapPayload {
+        recipient: info.sender.clone(),
+        denom: denom.clone(),
+        sell_type,
+        min_out,
+        held,
+    })?;
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("method", "withdraw_as")
+        .add_attribute("sender", user_addr)
+        .add_attribute("amount", amount)
+        .add_attribute("rewards", amount_with_reward - amount)
+        .add_attribute(format!("swap_out::{}", config.accepted_denom), payout)
+        .add_attribute("denom", denom))
+}
+
+// Removes an amount of the position with its rewards, returns them with the payout after a shutdown ratio
+fn remove_position(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+    user_addr: &str,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
     let mut user_account = USER_ACCOUNTS
         .may_load(deps.storage, user_addr)
         .unwrap()
         .unwrap_or_default();
 
+    check_withdraw_time(deps.as_ref(), env, config)?;
+
+    let mut global = load_global(deps.storage, env)?;
+    let ratio = exit_ratio(deps.as_ref(), env, config, &global)?;
+
+    // the balance is checked once the compounding and the losses are applied
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
     if user_account.deposited < amount {
         return Err(ContractError::InsufficientFunds {});
     }
+    check_unlocked(deps.as_ref(), config, &user_account, amount)?;
+    release_lock_after_shutdown(deps.storage, config, &mut user_account, &mut global)?;
 
-    let config = CONFIG.load(deps.storage)?;
+    let amount_with_reward = withdraw_with_reward(&mut user_account, &mut global, &amount);
+    let payout = apply_exit_ratio(amount_with_reward, ratio)?;
+
+    save_user_account(deps.storage, user_addr, &mut user_account, &global, env)?;
+    record_withdraw(deps.storage, user_addr, amount, amount_with_reward, &global)?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok((amount_with_reward, payout))
+}
+
+/* Withdraw requests are meant for the withdraw_time_buffer_secs window.
+The amount leaves the supply right away, so it is not used for the next bid,
+and can be claimed once the current auction is over and any bid is settled */
+pub fn request_withdraw(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    // Check if there is a bid, the amount may already be used for it
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        return Err(ContractError::ActiveBid {});
+    }
+
+    let user_addr = info.sender.as_str();
+
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, user_addr)?
+        .unwrap_or_default();
 
     let current_auction = get_current_auction(deps.as_ref())?;
-    let end_auction_ts =
-        Timestamp::from_seconds(u64::try_from(current_auction.auctionClosingTime)?);
+    let unlock_time = u64::try_from(current_auction.auctionClosingTime)?;
 
-    // Check if we are in the withdraw time
-    // We allow withdraws until the auctionClosingTime - withdraw_time_buffer_secs
-    if env.block.time > end_auction_ts.minus_seconds(config.withdraw_time_buffer_secs) {
-        let buffer_in_min = config.withdraw_time_buffer_secs / 60;
-        let end_time_in_min = end_auction_ts
-            .minus_seconds(env.block.time.seconds())
-            .seconds()
-            / 60;
-        return Err(ContractError::NotInWithdrawTime(
-            buffer_in_min,
-            end_time_in_min,
-        ));
+    let config = CONFIG.load(deps.storage)?;
+    let mut global = load_global(deps.storage, &env)?;
+
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+    if user_account.deposited < amount {
+        return Err(ContractError::InsufficientFunds {});
     }
+    check_unlocked(deps.as_ref(), &config, &user_account, amount)?;
+    release_lock_after_shutdown(deps.storage, &config, &mut user_account, &mut global)?;
 
-    let mut global = GLOBAL.load(deps.storage)?;
+    let amount_with_reward = withdraw_with_reward(&mut user_account, &mut global, &amount);
+    global.requested_withdrawals += amount_with_reward;
 
-    update_user_reward(&mut user_account, &global.index)?;
+    let mut withdraw_request = WITHDRAW_REQUESTS
+        .may_load(deps.storage, user_addr)?
+        .unwrap_or(WithdrawRequest {
+            amount: Uint128::zero(),
+            unlock_time,
+        });
+    withdraw_request.amount += amount_with_reward;
+    withdraw_request.unlock_time = withdraw_request.unlock_time.max(unlock_time);
 
-    let amount_with_reward = amount + user_account.pending_reward;
-    let msgs: Vec<CosmosMsg<_>> = vec![CosmosMsg::Bank(BankMsg::Send {
-        to_address: user_addr.to_string(),
-        amount: vec![Coin {
-            denom: config.accepted_denom.clone(),
-            amount: amount_with_reward,
-        }],
-    })];
+    WITHDRAW_REQUESTS.save(deps.storage, user_addr, &withdraw_request)?;
+    save_user_account(deps.storage, user_addr, &mut user_account, &global, &env)?;
+    record_withdraw(deps.storage, user_addr, amount, amount_with_reward, &global)?;
+    GLOBAL.save(deps.storage, &global)?;
 
-    user_account.pending_reward = Uint128::zero();
+    Ok(Response::new()
+        .add_attribute("method", "request_withdraw")
+        .add_attribute("owner", user_addr)
+        .add_attribute("amount", amount)
+        .add_attribute("rewards", amount_with_reward - amount)
+        .add_attribute("unlock_time", unlock_time.to_string()))
+}
 
-    decrease_supply(&mut user_account, &mut global, &amount);
+pub fn claim_withdraw(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let user_addr = info.sender.as_str();
 
-    if user_account.deposited.is_zero() {
-        USER_ACCOUNTS.remove(deps.storage, user_addr);
-    } else {
-        USER_ACCOUNTS.save(deps.storage, user_addr, &user_account)?;
+    let withdraw_request = WITHDRAW_REQUESTS
+        .may_load(deps.storage, user_addr)?
+        .ok_or(ContractError::WithdrawRequestNotFound {})?;
+
+    if env.block.time.seconds() < withdraw_request.unlock_time {
+        return Err(ContractError::WithdrawRequestLocked(
+            withdraw_request.unlock_time,
+        ));
     }
 
+    // the round needs to be settled first
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        return Err(ContractError::ActiveBid {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut global = GLOBAL.load(deps.storage)?;
+    let ratio = exit_ratio(deps.as_ref(), &env, &config, &global)?;
+
+    global.requested_withdrawals -= withdraw_request.amount;
+    let payout = apply_exit_ratio(withdraw_request.amount, ratio)?;
+
+    WITHDRAW_REQUESTS.remove(deps.storage, user_addr);
     GLOBAL.save(deps.storage, &global)?;
 
+    let mut msgs: Vec<CosmosMsg<_>> = vec![];
+    if payout > Uint128::zero() {
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: user_addr.to_string(),
+            amount: vec![Coin {
+                denom: config.accepted_denom,
+                amount: payout,
+            }],
+        }));
+    }
+
     Ok(Response::new()
         .add_messages(msgs)
-        .add_attribute("method", "withdraw")
+        .add_attribute("method", "claim_withdraw")
         .add_attribute("owner", user_addr)
-        .add_attribute("amount", amount)
-        .add_attribute("rewards", amount_with_reward - amount))
+        .add_attribute("amount", withdraw_request.amount)
+        .add_attribute("payout", payout))
 }
 
 pub fn harvest(
     deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     let user_addr = info.sender.as_str();
@@ -156,64 +548,487 @@ pub fn harvest(
         .unwrap()
         .unwrap_or_default();
 
-    let global = GLOBAL.load(deps.storage)?;
+    let mut global = load_global(deps.storage, &env)?;
 
-    update_user_reward(&mut user_account, &global.index)?;
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
 
     let config = CONFIG.load(deps.storage)?;
+    let ratio = exit_ratio(deps.as_ref(), &env, &config, &global)?;
+
+    // the referrer rewards are claimed together with the own rewards
+    let referrer_rewards = REFERRER_REWARDS.may_load(deps.storage, user_addr)?;
+    let referral_reward = referrer_rewards
+        .as_ref()
+        .map(|referrer_rewards| referrer_rewards.pending)
+        .unwrap_or_default();
+    if let Some(mut referrer_rewards) = referrer_rewards {
+        referrer_rewards.pending = Uint128::zero();
+        REFERRER_REWARDS.save(deps.storage, user_addr, &referrer_rewards)?;
+    }
 
     let mut msgs: Vec<CosmosMsg<_>> = vec![];
-    let reward = user_account.pending_reward;
-    if reward > Uint128::zero() {
+    let reward = user_account.pending_reward + referral_reward;
+    let payout = apply_exit_ratio(reward, ratio)?;
+    if payout > Uint128::zero() {
         msgs.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: user_addr.to_string(),
             amount: vec![Coin {
                 denom: config.accepted_denom.clone(),
-                amount: reward,
+                amount: payout,
             }],
         }));
     }
 
+    user_account.total_harvested += user_account.pending_reward;
     user_account.pending_reward = Uint128::zero();
+    global.rewards_settled += reward;
 
-    USER_ACCOUNTS.save(deps.storage, user_addr, &user_account)?;
+    save_user_account(deps.storage, user_addr, &mut user_account, &global, &env)?;
+    record_history(
+        deps.storage,
+        user_addr,
+        HistoryAction::Harvest,
+        reward,
+        &global,
+    )?;
+    GLOBAL.save(deps.storage, &global)?;
 
     Ok(Response::new()
         .add_messages(msgs)
         .add_attribute("method", "harvest")
         .add_attribute("owner", user_addr)
-        .add_attribute("amount", reward))
+        .add_attribute("amount", reward)
+        .add_attribute("referral_reward", referral_reward)
+        .add_attribute("payout", payout))
 }
 
-pub fn update_global_index(global: &mut Global) {
-    if global.total_supply.is_zero() {
-        return;
+/* Moves part of a position to another address, the pending rewards of both
+sides are settled first so the moved amount only earns for the new owner.
+The total supply doesn't change, so this is allowed during an active bid */
+pub fn transfer_position(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+    amount: Uint128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let to = deps.api.addr_validate(&to)?;
+    if to == info.sender {
+        return Err(ContractError::TransferToSelf {});
     }
 
-    global.index += Decimal256::from_ratio(global.profit_to_distribute, global.total_supply);
-    global.accumulated_profit += global.profit_to_distribute;
-    global.profit_to_distribute = Uint128::zero();
+    let user_addr = info.sender.as_str();
+
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, user_addr)?
+        .unwrap_or_default();
+
+    let mut recipient_account = USER_ACCOUNTS
+        .may_load(deps.storage, to.as_str())?
+        .unwrap_or_default();
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut global = load_global(deps.storage, &env)?;
+
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+    update_user_reward(deps.storage, &mut recipient_account, &mut global)?;
+    if user_account.deposited < amount {
+        return Err(ContractError::InsufficientFunds {});
+    }
+    check_unlocked(deps.as_ref(), &config, &user_account, amount)?;
+    release_lock_after_shutdown(deps.storage, &config, &mut user_account, &mut global)?;
+
+    decrease_supply(&mut user_account, &mut global, &amount);
+    increase_supply(&mut recipient_account, &mut global, &amount);
+
+    save_user_account(deps.storage, user_addr, &mut user_account, &global, &env)?;
+    save_user_account(
+        deps.storage,
+        to.as_str(),
+        &mut recipient_account,
+        &global,
+        &env,
+    )?;
+    record_history(
+        deps.storage,
+        user_addr,
+        HistoryAction::TransferOut,
+        amount,
+        &global,
+    )?;
+    record_history(
+        deps.storage,
+        to.as_str(),
+        HistoryAction::TransferIn,
+        amount,
+        &global,
+    )?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_position")
+        .add_attribute("sender", user_addr)
+        .add_attribute("beneficiary", to)
+        .add_attribute("amount", amount))
 }
 
+pub fn set_auto_compound(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let user_addr = info.sender.as_str();
+
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, user_addr)?
+        .unwrap_or_default();
+    let mut global = load_global(deps.storage, &env)?;
+
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+    if enabled && !user_account.lock_bonus.is_zero() {
+        return Err(ContractError::LockWithAutoCompound {});
+    }
+
+    if user_account.auto_compound != enabled {
+        if enabled {
+            global.compounding_supply += user_account.deposited;
+        } else {
+            global.compounding_supply = global
+                .compounding_supply
+                .saturating_sub(user_account.deposited);
+        }
+
+        user_account.auto_compound = enabled;
+        user_account.compound_index = global.compound_index;
+        user_account.compound_reward_index = global.compound_reward_index;
+    }
+
+    // the account is kept even without deposit, so the setting applies to the next one
+    record_reward_changes(deps.storage, user_addr, &user_account, &global)?;
+    credit_referral_fee(deps.storage, user_addr, &mut user_account)?;
+    USER_ACCOUNTS.save(deps.storage, user_addr, &user_account)?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_auto_compound")
+        .add_attribute("owner", user_addr)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+/* The profit is spread over the whole supply, the deposits sent during a bid wait
+in the queue so they don't dilute the round they didn't take part in.
+max_tokens caps how much of the compounding users reward can be added to the supply */
+pub fn update_global_index(
+    global: &mut Global,
+    profit: Uint128,
+    max_tokens: Uint128,
+) -> Result<(), ContractError> {
+    // remainder left over by the previous distributions, already counted as dust
+    let carried_dust = global.profit_to_distribute;
+    global.profit_to_distribute += profit;
+
+    // the locked deposits bonus weight is part of the supply earning the profit
+    let supply = global.total_supply + global.total_lock_bonus;
+
+    if supply.is_zero() {
+        return Ok(());
+    }
+
+    let delta = Decimal256::from_ratio(global.profit_to_distribute, supply);
+
+    /* delta is rounded down, the remainder is kept for the next distribution.
+    The users reward fractions add up to at most supply * delta, rounded up here
+    so that carrying them in reward_remainder never pays more than distributed */
+    let distributed =
+        Uint128::try_from((Decimal256::from_atomics(supply.u128(), 0)? * delta).to_uint_ceil())?
+            .min(global.profit_to_distribute);
+    let dust = global.profit_to_distribute - distributed;
+
+    // the index is per unit deposited before any loss, see apply_loss
+    global.index += delta * global.principal_factor;
+    global.accumulated_profit += distributed;
+    global.profit_to_distribute = dust;
+    global.accumulated_dust += dust.saturating_sub(carried_dust);
+
+    compound_rewards(global, delta, max_tokens)
+}
+
+/* Auto compounding users are not iterated, their principal grows lazily with
+global.compound_index. The part of their reward exceeding the max tokens
+stays claimable through global.compound_reward_index */
+fn compound_rewards(
+    global: &mut Global,
+    delta: Decimal256,
+    max_tokens: Uint128,
+) -> Result<(), ContractError> {
+    if global.compounding_supply.is_zero() || delta.is_zero() {
+        return Ok(());
+    }
+
+    let reward = Uint128::try_from(
+        (Decimal256::from_atomics(global.compounding_supply.u128(), 0)? * delta).to_uint_floor(),
+    )?;
+    let headroom = max_tokens.saturating_sub(global.total_supply);
+    let compounded = reward.min(headroom);
+
+    let compounded_ratio = if reward.is_zero() {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(compounded, reward)
+    };
+
+    global.compound_reward_index +=
+        global.compound_index * delta * (Decimal256::one() - compounded_ratio);
+    global.compound_index = global.compound_index * (Decimal256::one() + delta * compounded_ratio);
+
+    global.compounding_supply += compounded;
+    global.total_supply += compounded;
+    global.rewards_settled += compounded;
+
+    Ok(())
+}
+
+/* A loss reduces every principal pro rata through global.principal_factor,
+like compounding users it is applied lazily when the account is updated.
+The factor never reaches zero, so it is safe to divide by it */
+pub fn apply_loss(global: &mut Global, loss: Uint128) -> Result<Uint128, ContractError> {
+    let loss = loss.min(global.total_supply);
+    if loss.is_zero() {
+        return Ok(loss);
+    }
+
+    let kept_ratio = Decimal256::one() - Decimal256::from_ratio(loss, global.total_supply);
+
+    global.principal_factor = (global.principal_factor * kept_ratio).max(Decimal256::raw(1));
+    global.compound_index = (global.compound_index * kept_ratio).max(Decimal256::raw(1));
+    global.compounding_supply = Uint128::try_from(
+        (Decimal256::from_atomics(global.compounding_supply.u128(), 0)? * kept_ratio)
+            .to_uint_floor(),
+    )?;
+    global.total_lock_bonus = Uint128::try_from(
+        (Decimal256::from_atomics(global.total_lock_bonus.u128(), 0)? * kept_ratio).to_uint_floor(),
+    )?;
+    global.total_supply -= loss;
+    global.accumulated_loss += loss;
+
+    Ok(loss)
+}
+
+// The global changes only when a referral fee is taken from a compounded reward
 pub fn update_user_reward(
+    storage: &dyn Storage,
+    user_account: &mut UserAccount,
+    global: &mut Global,
+) -> Result<(), ContractError> {
+    let pending_reward = user_account.pending_reward;
+
+    let compounded = if user_account.auto_compound {
+        update_compounding_user_reward(user_account, global)?
+    } else {
+        update_weighted_user_reward(storage, user_account, global)?;
+        Uint128::zero()
+    };
+
+    take_referral_fee(storage, user_account, global, pending_reward, compounded)
+}
+
+fn update_weighted_user_reward(
+    storage: &dyn Storage,
+    user_account: &mut UserAccount,
+    global: &Global,
+) -> Result<(), ContractError> {
+    let deposited = Decimal256::from_atomics(user_account.deposited.u128(), 0)?;
+
+    let mut reward =
+        deposited * (global.index - user_account.index) / user_account.principal_factor;
+    let principal = Uint128::try_from(
+        (deposited * global.principal_factor / user_account.principal_factor).to_uint_floor(),
+    )?;
+
+    // the lock bonus earns until the index its lock expired at
+    if !user_account.lock_bonus.is_zero() {
+        let lock_bonus = Decimal256::from_atomics(user_account.lock_bonus.u128(), 0)?;
+
+        match EXPIRED_LOCK_INDEXES.may_load(storage, user_account.lock_until_round)? {
+            Some(expired_index) => {
+                reward += lock_bonus * (expired_index - user_account.index)
+                    / user_account.principal_factor;
+                user_account.lock_bonus = Uint128::zero();
+                user_account.locked = Uint128::zero();
+            }
+            None => {
+                reward += lock_bonus * (global.index - user_account.index)
+                    / user_account.principal_factor;
+                user_account.lock_bonus = Uint128::try_from(
+                    (lock_bonus * global.principal_factor / user_account.principal_factor)
+                        .to_uint_floor(),
+                )?;
+                user_account.locked = Uint128::try_from(
+                    (Decimal256::from_atomics(user_account.locked.u128(), 0)?
+                        * global.principal_factor
+                        / user_account.principal_factor)
+                        .to_uint_floor(),
+                )?;
+            }
+        }
+    }
+
+    user_account.total_loss += user_account.deposited - principal;
+    user_account.deposited = principal;
+    user_account.index = global.index;
+    user_account.principal_factor = global.principal_factor;
+    add_pending_reward(user_account, reward)
+}
+
+// the fraction of a reward below one unit is carried to the next update instead of being lost
+fn add_pending_reward(
     user_account: &mut UserAccount,
-    global_index: &Decimal256,
+    reward: Decimal256,
 ) -> Result<(), ContractError> {
-    let reward = Decimal256::from_atomics(user_account.deposited.u128(), 0)?
-        * (global_index - user_account.index);
+    let reward = reward + user_account.reward_remainder;
 
-    user_account.index = *global_index;
+    user_account.reward_remainder = reward - reward.floor();
     user_account.pending_reward += Uint128::try_from(reward.to_uint_floor())?;
 
     Ok(())
 }
 
-fn increase_supply(user_account: &mut UserAccount, global: &mut Global, amount: &Uint128) {
+// Returns the reward compounded into the principal
+fn update_compounding_user_reward(
+    user_account: &mut UserAccount,
+    global: &Global,
+) -> Result<Uint128, ContractError> {
+    let deposited = Decimal256::from_atomics(user_account.deposited.u128(), 0)?;
+
+    // compound_index is set when auto compounding gets enabled, so it is never zero here
+    let compounded_deposit = Uint128::try_from(
+        (deposited * global.compound_index / user_account.compound_index).to_uint_floor(),
+    )?;
+    let reward = deposited * (global.compound_reward_index - user_account.compound_reward_index)
+        / user_account.compound_index;
+
+    // the compound index also shrinks with the losses
+    let compounded = compounded_deposit.saturating_sub(user_account.deposited);
+    let loss = user_account.deposited.saturating_sub(compounded_deposit);
+
+    user_account.deposited = compounded_deposit;
+    user_account.total_compounded += compounded;
+    user_account.total_loss += loss;
+    user_account.principal_factor = global.principal_factor;
+    add_pending_reward(user_account, reward)?;
+    user_account.index = global.index;
+    user_account.compound_index = global.compound_index;
+    user_account.compound_reward_index = global.compound_reward_index;
+
+    Ok(compounded)
+}
+
+// removes the deposited amount together with the pending reward, returns the payout
+pub(crate) fn withdraw_with_reward(
+    user_account: &mut UserAccount,
+    global: &mut Global,
+    amount: &Uint128,
+) -> Uint128 {
+    let amount_with_reward = amount + user_account.pending_reward;
+
+    user_account.total_harvested += user_account.pending_reward;
+    global.rewards_settled += user_account.pending_reward;
+    user_account.pending_reward = Uint128::zero();
+
+    decrease_supply(user_account, global, amount);
+
+    amount_with_reward
+}
+
+// the principal and the rewards paid with it are recorded as separate entries
+pub(crate) fn record_withdraw(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    amount: Uint128,
+    amount_with_reward: Uint128,
+    global: &Global,
+) -> Result<(), ContractError> {
+    record_history(storage, user_addr, HistoryAction::Withdraw, amount, global)?;
+    record_history(
+        storage,
+        user_addr,
+        HistoryAction::Harvest,
+        amount_with_reward - amount,
+        global,
+    )
+}
+
+// deposits, queued deposits and withdraw requests owed to the depositors
+pub(crate) fn principal_liability(global: &Global) -> Uint128 {
+    global.total_supply + global.queued_deposits + global.requested_withdrawals
+}
+
+// distributed, undistributed and vesting profit not paid out or compounded yet
+pub(crate) fn reward_liability(global: &Global) -> Uint128 {
+    (global.accumulated_profit + global.profit_to_distribute + global.unvested_profit)
+        .saturating_sub(global.rewards_settled)
+}
+
+// the funds a bid can use, the withdrawals queued during a bid are paid once it settles
+pub(crate) fn pooled_funds(global: &Global) -> Uint128 {
+    (global.total_supply + global.profit_to_distribute + global.unvested_profit)
+        .saturating_sub(global.queued_withdrawals)
+}
+
+pub(crate) fn save_user_account(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    user_account: &mut UserAccount,
+    global: &Global,
+    env: &Env,
+) -> Result<(), ContractError> {
+    record_reward_changes(storage, user_addr, user_account, global)?;
+    credit_referral_fee(storage, user_addr, user_account)?;
+    record_balance(
+        storage,
+        user_addr,
+        user_account.deposited,
+        env.block.time.seconds(),
+    )?;
+    record_supply(storage, global, env.block.time.seconds())?;
+
+    // the pending reward is kept until it is harvested, the lock bonus until it expires
+    if user_account.deposited.is_zero()
+        && user_account.pending_reward.is_zero()
+        && user_account.lock_bonus.is_zero()
+    {
+        USER_ACCOUNTS.remove(storage, user_addr);
+    } else {
+        USER_ACCOUNTS.save(storage, user_addr, user_account)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn increase_supply(
+    user_account: &mut UserAccount,
+    global: &mut Global,
+    amount: &Uint128,
+) {
     user_account.deposited += amount;
     global.total_supply += amount;
+
+    if user_account.auto_compound {
+        global.compounding_supply += amount;
+    }
 }
 
-fn decrease_supply(user_account: &mut UserAccount, global: &mut Global, amount: &Uint128) {
+pub(crate) fn decrease_supply(
+    user_account: &mut UserAccount,
+    global: &mut Global,
+    amount: &Uint128,
+) {
     user_account.deposited -= amount;
     global.total_supply -= amount;
+
+    if user_account.auto_compound {
+        global.compounding_supply = global.compounding_supply.saturating_sub(*amount);
+    }
 }
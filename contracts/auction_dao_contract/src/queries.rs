@@ -1,15 +1,45 @@
 use crate::{
     auction::{
-        get_current_auction, get_current_auction_value_using_exchange,
-        get_current_auction_value_using_router,
+        get_bid_capacity, get_current_auction, get_current_auction_value_using_exchange,
+        get_current_auction_value_using_router, policy_value, valuation_divergence_bps,
     },
     exchange::simulate,
-    lp::{get_max_tokens, update_user_reward},
-    state::{CONFIG, GLOBAL, USER_ACCOUNTS},
+    history::total_earned,
+    lp::{get_max_tokens, principal_liability, reward_liability, update_user_reward},
+    shutdown::exit_ratio,
+    state::{
+        BID_ATTEMPT, CONFIG, DAO_STATS, GLOBAL, GLOBAL_STATS, QUEUED_ACTIONS, RECEIPT_DENOM,
+        REFERRALS, REFERRER_REWARDS, ROUND_HISTORY, ROUND_LOSSES, USER_ACCOUNTS, USER_HISTORY,
+        USER_STATS, VAULT_ACCOUNT, WITHDRAW_REQUESTS,
+    },
+    stats::{dao_stats, performance},
+    vesting::{load_global, vested_profit},
+};
+use auction_dao::{
+    error::ContractError,
+    msg::{
+        BasketValuationResponse, EligibilityResponse, RefereeInfo, ReferralsResponse,
+        RoundsResponse, ShutdownStatusResponse, SolvencyResponse, UserHistoryEntry,
+        UserHistoryResponse, UserInfo, UserRewardsResponse, UsersResponse, VaultResponse,
+        VestingResponse,
+    },
+    state::{Config, RoundRecord, UserAccount},
 };
-use auction_dao::error::ContractError;
-use cosmwasm_std::{to_json_binary, Binary, Deps, Uint128};
-use injective_cosmwasm::InjectiveQueryWrapper;
+use cosmwasm_std::{
+    to_json_binary, Binary, Coin, Decimal256, Deps, Env, Order, StdResult, Uint128,
+};
+use cw_storage_plus::Bound;
+use injective_cosmwasm::{InjectiveQuerier, InjectiveQueryWrapper};
+use std::str::FromStr;
+
+pub const DEFAULT_REFERRALS_LIMIT: u32 = 10;
+pub const MAX_REFERRALS_LIMIT: u32 = 30;
+pub const DEFAULT_HISTORY_LIMIT: u32 = 10;
+pub const MAX_HISTORY_LIMIT: u32 = 50;
+pub const DEFAULT_USERS_LIMIT: u32 = 10;
+pub const MAX_USERS_LIMIT: u32 = 30;
+pub const DEFAULT_ROUNDS_LIMIT: u32 = 10;
+pub const MAX_ROUNDS_LIMIT: u32 = 30;
 
 pub fn query_current_auction_basket(
     deps: Deps<InjectiveQueryWrapper>,
@@ -21,18 +51,365 @@ pub fn query_current_auction_basket(
 
 pub fn query_user(
     deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
     address: String,
 ) -> Result<Binary, ContractError> {
     let mut user_account = USER_ACCOUNTS
         .may_load(deps.storage, &address)?
         .unwrap_or_default();
-    let global = GLOBAL.load(deps.storage)?;
+    let mut global = load_global(deps.storage, &env)?;
 
-    update_user_reward(&mut user_account, &global.index)?;
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
 
     Ok(to_json_binary(&user_account)?)
 }
 
+pub fn query_user_rewards(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+    address: String,
+) -> Result<Binary, ContractError> {
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let mut global = load_global(deps.storage, &env)?;
+
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+    Ok(to_json_binary(&UserRewardsResponse {
+        auto_compound: user_account.auto_compound,
+        pending_reward: user_account.pending_reward,
+        total_compounded: user_account.total_compounded,
+        total_harvested: user_account.total_harvested,
+    })?)
+}
+
+pub fn query_queued_actions(
+    deps: Deps<InjectiveQueryWrapper>,
+    address: String,
+) -> Result<Binary, ContractError> {
+    let queued_action = QUEUED_ACTIONS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+
+    Ok(to_json_binary(&queued_action)?)
+}
+
+pub fn query_withdraw_request(
+    deps: Deps<InjectiveQueryWrapper>,
+    address: String,
+) -> Result<Binary, ContractError> {
+    let withdraw_request = WITHDRAW_REQUESTS.may_load(deps.storage, &address)?;
+
+    Ok(to_json_binary(&withdraw_request)?)
+}
+
+pub fn query_round_loss(
+    deps: Deps<InjectiveQueryWrapper>,
+    round: u64,
+) -> Result<Binary, ContractError> {
+    let loss = ROUND_LOSSES
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+
+    Ok(to_json_binary(&loss)?)
+}
+
+// Deposits sent during an active bid wait in the queue, so they are not eligible for its round
+pub fn query_eligibility(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+    address: String,
+) -> Result<Binary, ContractError> {
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let mut global = load_global(deps.storage, &env)?;
+
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+    let queued_action = QUEUED_ACTIONS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let bid_attempt = BID_ATTEMPT.may_load(deps.storage)?;
+
+    Ok(to_json_binary(&EligibilityResponse {
+        eligible: user_account.deposited,
+        ineligible: queued_action.deposit,
+        round: bid_attempt.as_ref().map(|bid_attempt| bid_attempt.round),
+        eligible_supply: bid_attempt.map(|_| global.total_supply + global.total_lock_bonus),
+    })?)
+}
+
+pub fn query_referrals(
+    deps: Deps<InjectiveQueryWrapper>,
+    referrer: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let limit = limit
+        .unwrap_or(DEFAULT_REFERRALS_LIMIT)
+        .min(MAX_REFERRALS_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let referees = REFERRALS
+        .prefix(referrer.as_str())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(address, earned)| RefereeInfo { address, earned }))
+        .collect::<StdResult<Vec<RefereeInfo>>>()?;
+
+    let referrer_rewards = REFERRER_REWARDS
+        .may_load(deps.storage, referrer.as_str())?
+        .unwrap_or_default();
+
+    Ok(to_json_binary(&ReferralsResponse {
+        pending: referrer_rewards.pending,
+        total_earned: referrer_rewards.total_earned,
+        referees,
+    })?)
+}
+
+// Ordered by address, start_after is the last address received
+pub fn query_users(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_USERS_LIMIT).min(MAX_USERS_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let mut global = load_global(deps.storage, &env)?;
+
+    let accounts = USER_ACCOUNTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<(String, UserAccount)>>>()?;
+
+    let mut users = vec![];
+    for (address, mut user_account) in accounts {
+        update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+        users.push(UserInfo {
+            address,
+            deposited: user_account.deposited,
+            pending_reward: user_account.pending_reward,
+        });
+    }
+
+    Ok(to_json_binary(&UsersResponse { users })?)
+}
+
+/* The distributed profit not harvested or compounded yet, without iterating the users.
+It includes the referrer rewards and the vault reward not compounded yet, and some
+dust left by rounding the user rewards down */
+pub fn query_total_pending_rewards(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+) -> Result<Binary, ContractError> {
+    let global = load_global(deps.storage, &env)?;
+
+    Ok(to_json_binary(
+        &global
+            .accumulated_profit
+            .saturating_sub(global.rewards_settled),
+    )?)
+}
+
+pub fn query_stats(deps: Deps<InjectiveQueryWrapper>) -> Result<Binary, ContractError> {
+    let stats = DAO_STATS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(to_json_binary(&dao_stats(&stats))?)
+}
+
+// Oldest rounds first, only the rounds the contract bid in are recorded
+pub fn query_rounds(
+    deps: Deps<InjectiveQueryWrapper>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_ROUNDS_LIMIT).min(MAX_ROUNDS_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let rounds = ROUND_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<RoundRecord>>>()?;
+
+    Ok(to_json_binary(&RoundsResponse { rounds })?)
+}
+
+// Oldest entries first, start_after is the id of the last entry received
+pub fn query_user_history(
+    deps: Deps<InjectiveQueryWrapper>,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let entries = USER_HISTORY
+        .prefix(address.as_str())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(id, entry)| UserHistoryEntry {
+                id,
+                action: entry.action,
+                amount: entry.amount,
+                last_settled_round: entry.last_settled_round,
+            })
+        })
+        .collect::<StdResult<Vec<UserHistoryEntry>>>()?;
+
+    Ok(to_json_binary(&UserHistoryResponse { entries })?)
+}
+
+// The rewards accrued since the last account update are included
+pub fn query_user_performance(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+    address: String,
+) -> Result<Binary, ContractError> {
+    let stored_account = USER_ACCOUNTS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let mut global = load_global(deps.storage, &env)?;
+
+    let mut user_account = stored_account.clone();
+    update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+    let stats = USER_STATS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let realized_rewards =
+        stats.total_earned + total_earned(&user_account) - total_earned(&stored_account);
+    let total_loss = stats.total_loss + user_account.total_loss - stored_account.total_loss;
+
+    Ok(to_json_binary(&performance(
+        &stats,
+        realized_rewards,
+        total_loss,
+        env.block.time.seconds(),
+    )?)?)
+}
+
+pub fn query_global_performance(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+) -> Result<Binary, ContractError> {
+    let global = load_global(deps.storage, &env)?;
+    let stats = GLOBAL_STATS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(to_json_binary(&performance(
+        &stats,
+        global.accumulated_profit,
+        global.accumulated_loss,
+        env.block.time.seconds(),
+    )?)?)
+}
+
+/* Compares what the contract holds in the accepted denom with what it owes the depositors.
+The rewards owed are the distributed and undistributed profit minus the rewards already
+paid out or compounded, the index rounding down only leaves some dust in the surplus */
+pub fn query_solvency(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let global = GLOBAL.load(deps.storage)?;
+
+    let bank_balance = deps
+        .querier
+        .query_balance(&env.contract.address, &config.accepted_denom)?
+        .amount;
+    let inventory = held_inventory(deps, &env, &config)?;
+
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let subaccount_deposit = querier
+        .query_subaccount_deposit(&config.contract_subaccount_id, &config.accepted_denom)?
+        .deposits;
+    let subaccount_balance =
+        Uint128::from_str(&subaccount_deposit.total_balance.int().to_string())?;
+
+    /* the bid amount is escrowed in the auction module while the contract is the highest
+    bidder. Once outbid it is refunded to the bank, once won it paid for the basket */
+    let current_auction = get_current_auction(deps)?;
+    let active_bid = match BID_ATTEMPT.may_load(deps.storage)? {
+        Some(bid_attempt)
+            if bid_attempt.round == current_auction.auctionRound
+                && current_auction.highestBidder == env.contract.address.as_str() =>
+        {
+            bid_attempt.amount
+        }
+        _ => Uint128::zero(),
+    };
+
+    let principal_liability = principal_liability(&global);
+    let reward_liability = reward_liability(&global);
+
+    let assets = bank_balance + subaccount_balance + active_bid;
+    let liabilities = principal_liability + reward_liability;
+
+    Ok(to_json_binary(&SolvencyResponse {
+        bank_balance,
+        subaccount_balance,
+        active_bid,
+        principal_liability,
+        reward_liability,
+        inventory,
+        surplus: assets.saturating_sub(liabilities),
+        deficit: liabilities.saturating_sub(assets),
+    })?)
+}
+
+pub fn query_vesting(deps: Deps<InjectiveQueryWrapper>, env: Env) -> Result<Binary, ContractError> {
+    let global = GLOBAL.load(deps.storage)?;
+    let vested = vested_profit(&global, env.block.time.seconds());
+
+    Ok(to_json_binary(&VestingResponse {
+        unvested_profit: global.unvested_profit,
+        vested_profit: vested,
+        vesting_end: global.vesting_end,
+    })?)
+}
+
+// The basket assets not sold yet can still be swapped with ManualExchangeSwap
+pub fn query_shutdown_status(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let global = GLOBAL.load(deps.storage)?;
+
+    Ok(to_json_binary(&ShutdownStatusResponse {
+        shutdown: config.shutdown,
+        exit_ratio: match exit_ratio(deps, &env, &config, &global) {
+            Err(ContractError::ActiveBid {}) => None,
+            ratio => Some(ratio?),
+        },
+        unsold_assets: held_inventory(deps, &env, &config)?,
+    })?)
+}
+
+// other denoms than the accepted one held by the contract
+fn held_inventory(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+) -> Result<Vec<Coin>, ContractError> {
+    let balances = deps.querier.query_all_balances(&env.contract.address)?;
+
+    Ok(balances
+        .into_iter()
+        .filter(|coin| coin.denom != config.accepted_denom && !coin.amount.is_zero())
+        .collect())
+}
+
 pub fn query_current_auction_value_using_router(
     deps: Deps<InjectiveQueryWrapper>,
 ) -> Result<Binary, ContractError> {
@@ -60,9 +437,57 @@ pub fn query_current_auction_value_using_exchange(
     Ok(to_json_binary(&total_value)?)
 }
 
+// Both values are computed whatever the policy, value is none when the policy rejects them
+pub fn query_basket_valuation(deps: Deps<InjectiveQueryWrapper>) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let router_value = get_current_auction_value_using_router(deps)?;
+    let exchange_value = get_current_auction_value_using_exchange(deps)?;
+
+    Ok(to_json_binary(&BasketValuationResponse {
+        router_value,
+        exchange_value,
+        divergence_bps: valuation_divergence_bps(router_value, exchange_value),
+        value: policy_value(&config.valuation_policy, router_value, exchange_value).ok(),
+        policy: config.valuation_policy,
+    })?)
+}
+
+pub fn query_bid_capacity(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: Env,
+) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let global = load_global(deps.storage, &env)?;
+
+    Ok(to_json_binary(&get_bid_capacity(
+        deps, &env, &config, &global,
+    )?)?)
+}
+
 pub fn query_max_tokens(deps: Deps<InjectiveQueryWrapper>) -> Result<Binary, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let max_tokens = get_max_tokens(deps, &config)?;
 
     Ok(to_json_binary(&max_tokens)?)
 }
+
+pub fn query_vault(deps: Deps<InjectiveQueryWrapper>, env: Env) -> Result<Binary, ContractError> {
+    let mut global = load_global(deps.storage, &env)?;
+    let mut vault = VAULT_ACCOUNT.may_load(deps.storage)?.unwrap_or_default();
+
+    update_user_reward(deps.storage, &mut vault, &mut global)?;
+
+    let total_assets = vault.deposited + vault.pending_reward;
+    let exchange_rate = if global.total_shares.is_zero() {
+        Decimal256::one()
+    } else {
+        Decimal256::from_ratio(total_assets, global.total_shares)
+    };
+
+    Ok(to_json_binary(&VaultResponse {
+        receipt_denom: RECEIPT_DENOM.may_load(deps.storage)?,
+        total_shares: global.total_shares,
+        total_assets,
+        exchange_rate,
+    })?)
+}
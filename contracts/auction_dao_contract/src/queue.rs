@@ -0,0 +1,235 @@
+use auction_dao::error::ContractError;
+use auction_dao::msg::{ExecuteMsg, PROCESS_QUEUE_REPLY_ID};
+use auction_dao::state::{Global, HistoryAction, QueuedAction};
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, DepsMut, Env, Order, Response, StdResult,
+    SubMsg, Uint128, WasmMsg,
+};
+use injective_cosmwasm::{create_mint_tokens_msg, InjectiveMsgWrapper, InjectiveQueryWrapper};
+
+use crate::{
+    history::record_history,
+    lock::{check_unlocked, release_lock_after_shutdown},
+    lp::{
+        increase_supply, record_withdraw, save_user_account, update_user_reward,
+        withdraw_with_reward,
+    },
+    shutdown::{apply_exit_ratio, exit_ratio},
+    state::{BID_ATTEMPT, CONFIG, GLOBAL, QUEUED_ACTIONS, RECEIPT_DENOM, USER_ACCOUNTS},
+    vault::issue_shares,
+    vesting::load_global,
+};
+
+pub const DEFAULT_PROCESS_QUEUE_LIMIT: u32 = 30;
+
+/* Deposits and withdrawals sent while a bid is active are queued.
+The queued deposits are escrowed in the contract and don't take part in the
+round, the queued withdrawals keep earning until the round is settled */
+
+pub fn queue_deposit(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    beneficiary: &Addr,
+    amount: Uint128,
+    mut global: Global,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let mut queued_action = QUEUED_ACTIONS
+        .may_load(deps.storage, beneficiary.as_str())?
+        .unwrap_or_default();
+
+    queued_action.deposit += amount;
+    global.queued_deposits += amount;
+
+    QUEUED_ACTIONS.save(deps.storage, beneficiary.as_str(), &queued_action)?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "queue_deposit")
+        .add_attribute("sender", sender)
+        .add_attribute("beneficiary", beneficiary)
+        .add_attribute("amount", amount))
+}
+
+pub fn queue_vault_deposit(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    amount: Uint128,
+    mut global: Global,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let mut queued_action = QUEUED_ACTIONS
+        .may_load(deps.storage, sender.as_str())?
+        .unwrap_or_default();
+
+    queued_action.vault_deposit += amount;
+    global.queued_deposits += amount;
+
+    QUEUED_ACTIONS.save(deps.storage, sender.as_str(), &queued_action)?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "queue_vault_deposit")
+        .add_attribute("owner", sender)
+        .add_attribute("amount", amount))
+}
+
+pub fn queue_withdraw(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: &Env,
+    sender: &Addr,
+    beneficiary: &Addr,
+    amount: Uint128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let mut user_account = USER_ACCOUNTS
+        .may_load(deps.storage, sender.as_str())?
+        .unwrap_or_default();
+
+    let mut queued_action = QUEUED_ACTIONS
+        .may_load(deps.storage, sender.as_str())?
+        .unwrap_or_default();
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut global = load_global(deps.storage, env)?;
+
+    // only to check the current balance, the account is settled when the queue is processed
+    update_user_reward(deps.storage, &mut user_account, &mut global.clone())?;
+
+    if user_account.deposited < queued_action.withdraw + amount {
+        return Err(ContractError::InsufficientFunds {});
+    }
+
+    check_unlocked(
+        deps.as_ref(),
+        &config,
+        &user_account,
+        queued_action.withdraw + amount,
+    )?;
+
+    queued_action.withdraw += amount;
+    // the whole queued withdraw goes to the latest recipient
+    queued_action.withdraw_recipient = if beneficiary == sender {
+        None
+    } else {
+        Some(beneficiary.clone())
+    };
+    global.queued_withdrawals += amount;
+
+    QUEUED_ACTIONS.save(deps.storage, sender.as_str(), &queued_action)?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "queue_withdraw")
+        .add_attribute("sender", sender)
+        .add_attribute("beneficiary", beneficiary)
+        .add_attribute("amount", amount))
+}
+
+pub fn process_queue(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    limit: Option<u32>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        return Err(ContractError::ActiveBid {});
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_PROCESS_QUEUE_LIMIT) as usize;
+    let queued_actions = QUEUED_ACTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<(String, QueuedAction)>>>()?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut global = load_global(deps.storage, &env)?;
+    let ratio = exit_ratio(deps.as_ref(), &env, &config, &global)?;
+    let mut msgs: Vec<CosmosMsg<InjectiveMsgWrapper>> = vec![];
+
+    for (user_addr, queued_action) in queued_actions.iter() {
+        let mut user_account = USER_ACCOUNTS
+            .may_load(deps.storage, user_addr)?
+            .unwrap_or_default();
+
+        update_user_reward(deps.storage, &mut user_account, &mut global)?;
+
+        global.queued_deposits -= queued_action.deposit;
+        increase_supply(&mut user_account, &mut global, &queued_action.deposit);
+
+        global.queued_withdrawals -= queued_action.withdraw;
+        let amount = queued_action.withdraw.min(user_account.deposited);
+        let mut amount_with_reward = Uint128::zero();
+        if !amount.is_zero() {
+            release_lock_after_shutdown(deps.storage, &config, &mut user_account, &mut global)?;
+            amount_with_reward = withdraw_with_reward(&mut user_account, &mut global, &amount);
+
+            let recipient = match &queued_action.withdraw_recipient {
+                Some(recipient) => recipient.to_string(),
+                None => user_addr.to_string(),
+            };
+
+            let payout = apply_exit_ratio(amount_with_reward, ratio)?;
+            if !payout.is_zero() {
+                msgs.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: recipient,
+                    amount: vec![Coin {
+                        denom: config.accepted_denom.clone(),
+                        amount: payout,
+                    }],
+                }));
+            }
+        }
+
+        // a vault wiped out during the round can't price the shares, the deposit is refunded
+        if !queued_action.vault_deposit.is_zero() {
+            global.queued_deposits -= queued_action.vault_deposit;
+
+            match issue_shares(deps.storage, &mut global, queued_action.vault_deposit)? {
+                Some(shares) => msgs.push(create_mint_tokens_msg(
+                    env.contract.address.clone(),
+                    Coin {
+                        denom: RECEIPT_DENOM.load(deps.storage)?,
+                        amount: shares,
+                    },
+                    user_addr.to_string(),
+                )),
+                None => msgs.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: user_addr.to_string(),
+                    amount: vec![Coin {
+                        denom: config.accepted_denom.clone(),
+                        amount: queued_action.vault_deposit,
+                    }],
+                })),
+            }
+        }
+
+        save_user_account(deps.storage, user_addr, &mut user_account, &global, &env)?;
+        record_history(
+            deps.storage,
+            user_addr,
+            HistoryAction::Deposit,
+            queued_action.deposit,
+            &global,
+        )?;
+        record_withdraw(deps.storage, user_addr, amount, amount_with_reward, &global)?;
+        QUEUED_ACTIONS.remove(deps.storage, user_addr);
+    }
+
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("method", "process_queue")
+        .add_attribute("processed", queued_actions.len().to_string()))
+}
+
+// processing the queue after a bid is cleared is best effort, a failure is
+// caught in the reply so it never reverts the settlement
+pub fn create_process_queue_message(
+    contract_addr: &str,
+) -> Result<SubMsg<InjectiveMsgWrapper>, ContractError> {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract_addr.to_string(),
+        msg: to_json_binary(&ExecuteMsg::ProcessQueue { limit: None })?,
+        funds: vec![],
+    });
+
+    Ok(SubMsg::reply_on_error(msg, PROCESS_QUEUE_REPLY_ID))
+}
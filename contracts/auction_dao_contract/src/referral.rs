@@ -0,0 +1,103 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::{Global, UserAccount};
+use cosmwasm_std::{Addr, Storage, Uint128};
+
+use crate::state::{CONFIG, REFERRALS, REFERRER_REWARDS};
+
+/* The referrer of an account is set on its first deposit with a referrer.
+A share of every reward of the referee is taken in update_user_reward and
+credited to the referrer when the referee account is saved */
+
+pub fn validate_referral_reward_bps(referral_reward_bps: Uint128) -> Result<(), ContractError> {
+    if referral_reward_bps > Uint128::new(10000) {
+        return Err(ContractError::InvalidReferralRewardBps {});
+    }
+
+    Ok(())
+}
+
+// The referee reward must be up to date, so the referrer doesn't share the rewards earned before
+pub fn set_referrer(
+    storage: &mut dyn Storage,
+    referee: &Addr,
+    user_account: &mut UserAccount,
+    referrer: &Addr,
+) -> Result<(), ContractError> {
+    if referee == referrer {
+        return Err(ContractError::InvalidReferrer {});
+    }
+
+    // the first referrer is kept
+    if user_account.referrer.is_some() {
+        return Ok(());
+    }
+
+    user_account.referrer = Some(referrer.clone());
+
+    REFERRALS.save(
+        storage,
+        (referrer.as_str(), referee.as_str()),
+        &Uint128::zero(),
+    )?;
+
+    Ok(())
+}
+
+/* Both the claimable and the compounded parts of the reward are shared. The share of the
+compounded part leaves the principal and is owed again as a reward, to the referrer */
+pub fn take_referral_fee(
+    storage: &dyn Storage,
+    user_account: &mut UserAccount,
+    global: &mut Global,
+    pending_reward_before: Uint128,
+    compounded: Uint128,
+) -> Result<(), ContractError> {
+    if user_account.referrer.is_none() {
+        return Ok(());
+    }
+
+    let config = CONFIG.load(storage)?;
+
+    let reward = user_account.pending_reward - pending_reward_before;
+    let fee = reward.multiply_ratio(config.referral_reward_bps, Uint128::new(10000));
+    let compounded_fee = compounded.multiply_ratio(config.referral_reward_bps, Uint128::new(10000));
+
+    user_account.pending_reward -= fee;
+    user_account.deposited -= compounded_fee;
+    user_account.total_compounded -= compounded_fee;
+    user_account.referral_fee += fee + compounded_fee;
+
+    global.total_supply -= compounded_fee;
+    global.compounding_supply = global.compounding_supply.saturating_sub(compounded_fee);
+    global.rewards_settled -= compounded_fee;
+
+    Ok(())
+}
+
+pub fn credit_referral_fee(
+    storage: &mut dyn Storage,
+    referee: &str,
+    user_account: &mut UserAccount,
+) -> Result<(), ContractError> {
+    let fee = user_account.referral_fee;
+    let referrer = match &user_account.referrer {
+        Some(referrer) if !fee.is_zero() => referrer.as_str(),
+        _ => return Ok(()),
+    };
+
+    let mut referrer_rewards = REFERRER_REWARDS
+        .may_load(storage, referrer)?
+        .unwrap_or_default();
+    referrer_rewards.pending += fee;
+    referrer_rewards.total_earned += fee;
+    REFERRER_REWARDS.save(storage, referrer, &referrer_rewards)?;
+
+    let earned = REFERRALS
+        .may_load(storage, (referrer, referee))?
+        .unwrap_or_default();
+    REFERRALS.save(storage, (referrer, referee), &(earned + fee))?;
+
+    user_account.referral_fee = Uint128::zero();
+
+    Ok(())
+}
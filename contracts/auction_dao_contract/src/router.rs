@@ -1,10 +1,11 @@
 use std::str::FromStr;
 
 use crate::state::{read_swap_route, CONFIG};
+use auction_dao::error::ContractError;
 use auction_dao::types::{
     AssetInfo, OfferAsset, RouterSimulation, RouterSimulationQuerry, RouterSimulationQuerryResponse,
 };
-use cosmwasm_std::{Deps, StdResult, Uint128};
+use cosmwasm_std::{Deps, Uint128};
 use injective_cosmwasm::InjectiveQueryWrapper;
 
 pub(crate) fn get_inj_value_asset(
@@ -12,21 +13,16 @@ pub(crate) fn get_inj_value_asset(
     source_denom: String,
     target_denom: String,
     amount: String,
-) -> StdResult<Uint128> {
+) -> Result<Uint128, ContractError> {
     if source_denom == "inj" {
-        return Uint128::from_str(&amount);
+        return Ok(Uint128::from_str(&amount)?);
     }
+
+    // an asset without a route can't be sold, it is valued at zero like in the exchange valuation
     let market_id = match read_swap_route(deps, &source_denom, &target_denom) {
-        Ok(route) => route,
-        Err(_e) => {
-            // deps.api.debug(&format!(
-            //     "Error, Probably market_id not registered  {:?}",
-            //     _e
-            // ));
-            return Uint128::from_str(&"0");
-        }
-    }
-    .market_id;
+        Ok(route) => route.market_id,
+        Err(_) => return Ok(Uint128::zero()),
+    };
 
     let config = CONFIG.load(deps.storage)?;
 
@@ -52,17 +48,10 @@ pub(crate) fn get_inj_value_asset(
         },
     };
 
-    let output_amount_response: RouterSimulationQuerryResponse = match deps
+    // a failing simulation must not value the asset at zero
+    let output_amount_response: RouterSimulationQuerryResponse = deps
         .querier
-        .query_wasm_smart(config.swap_router, &querry_output_message)
-    {
-        Ok(response) => response,
-        Err(_e) => {
-            // deps.api.debug(&format!("query_wasm_smart error: {:?}", e));
-
-            RouterSimulationQuerryResponse::default()
-        }
-    };
+        .query_wasm_smart(config.swap_router, &querry_output_message)?;
 
     Ok(Uint128::from_str(&output_amount_response.return_amount)?)
 }
@@ -0,0 +1,81 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::{Config, Global};
+use cosmwasm_std::{Addr, Decimal256, Deps, DepsMut, Env, Response, Uint128};
+use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper};
+
+use crate::{
+    admins::verify_sender_is_admin,
+    lp::{principal_liability, reward_liability},
+    state::{BID_ATTEMPT, CONFIG},
+};
+
+/* After a shutdown the contract doesn't bid or take deposits anymore and the depositors
+can leave at any time. When the contract holds less than it owes, every payout is reduced
+by the same ratio, so the depositors leaving last get the same share as the first ones.
+A bid sent before the shutdown is neither in the balance nor lost yet, so nothing is paid
+out until its round is settled */
+
+pub fn shutdown(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+
+    CONFIG.update(deps.storage, |mut c| -> Result<_, ContractError> {
+        if c.shutdown {
+            return Err(ContractError::Shutdown {});
+        }
+
+        c.shutdown = true;
+
+        Ok(c)
+    })?;
+
+    Ok(Response::new().add_attribute("method", "shutdown"))
+}
+
+pub fn ensure_not_shutdown(config: &Config) -> Result<(), ContractError> {
+    if config.shutdown {
+        return Err(ContractError::Shutdown {});
+    }
+
+    Ok(())
+}
+
+// Must be computed before the payouts are removed from the liabilities
+pub fn exit_ratio(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+    global: &Global,
+) -> Result<Decimal256, ContractError> {
+    if !config.shutdown {
+        return Ok(Decimal256::one());
+    }
+
+    if BID_ATTEMPT.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::ActiveBid {});
+    }
+
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &config.accepted_denom)?
+        .amount;
+    let liabilities = principal_liability(global) + reward_liability(global);
+
+    if balance >= liabilities {
+        return Ok(Decimal256::one());
+    }
+
+    Ok(Decimal256::from_ratio(balance, liabilities))
+}
+
+pub fn apply_exit_ratio(amount: Uint128, exit_ratio: Decimal256) -> Result<Uint128, ContractError> {
+    if exit_ratio == Decimal256::one() {
+        return Ok(amount);
+    }
+
+    Ok(Uint128::try_from(
+        (Decimal256::from_atomics(amount.u128(), 0)? * exit_ratio).to_uint_floor(),
+    )?)
+}
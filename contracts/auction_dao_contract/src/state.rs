@@ -1,8 +1,11 @@
 use auction_dao::{
     error::ContractError,
-    state::{BidAttempt, Config, Global, SwapRoute, UserAccount},
+    state::{
+        BidAttempt, Config, DaoStats, Global, HistoryEntry, PositionStats, QueuedAction,
+        ReferrerRewards, RoundRecord, SwapRoute, UserAccount, WithdrawRequest,
+    },
 };
-use cosmwasm_std::{Deps, DepsMut, Order, StdResult, Storage, Uint128};
+use cosmwasm_std::{Decimal256, Deps, DepsMut, Order, StdResult, Storage, Uint128};
 use cw_storage_plus::{Item, Map};
 use injective_cosmwasm::InjectiveQueryWrapper;
 
@@ -13,6 +16,27 @@ pub const BID_ATTEMPT_TRANSIENT: Item<BidAttempt> = Item::new("bid_attempt_trans
 pub const SETTLED_AMOUNT_TRANSIENT: Item<Uint128> = Item::new("settled_amount_transient");
 pub const USER_ACCOUNTS: Map<&str, UserAccount> = Map::new("user_accounts");
 pub const SWAP_ROUTES: Map<(String, String), SwapRoute> = Map::new("swap_routes");
+pub const VAULT_ACCOUNT: Item<UserAccount> = Item::new("vault_account");
+pub const QUEUED_ACTIONS: Map<&str, QueuedAction> = Map::new("queued_actions");
+pub const WITHDRAW_REQUESTS: Map<&str, WithdrawRequest> = Map::new("withdraw_requests");
+pub const RECEIPT_DENOM: Item<String> = Item::new("receipt_denom");
+pub const ROUND_LOSSES: Map<u64, Uint128> = Map::new("round_losses");
+// lock bonus expiring at each round, normalized by the principal factor
+pub const LOCK_EXPIRIES: Map<u64, Uint128> = Map::new("lock_expiries");
+pub const REFERRER_REWARDS: Map<&str, ReferrerRewards> = Map::new("referrer_rewards");
+// (referrer, referee) -> rewards earned by the referrer from the referee
+pub const REFERRALS: Map<(&str, &str), Uint128> = Map::new("referrals");
+// global index when the locks of a round expired
+pub const EXPIRED_LOCK_INDEXES: Map<u64, Decimal256> = Map::new("expired_lock_indexes");
+// (user, entry id) -> history entry, ids start at 1 for each user
+pub const USER_HISTORY: Map<(&str, u64), HistoryEntry> = Map::new("user_history");
+pub const USER_HISTORY_LENGTHS: Map<&str, u64> = Map::new("user_history_lengths");
+pub const USER_STATS: Map<&str, PositionStats> = Map::new("user_stats");
+pub const GLOBAL_STATS: Item<PositionStats> = Item::new("global_stats");
+pub const ROUND_HISTORY: Map<u64, RoundRecord> = Map::new("round_history");
+pub const DAO_STATS: Item<DaoStats> = Item::new("dao_stats");
+
+pub const RECEIPT_SUBDENOM: &str = "adinj";
 
 pub fn store_swap_route(storage: &mut dyn Storage, route: &SwapRoute) -> StdResult<()> {
     let key = route_key(&route.source_denom, &route.target_denom);
@@ -0,0 +1,205 @@
+use auction_dao::error::ContractError;
+use auction_dao::msg::{PerformanceResponse, StatsResponse};
+use auction_dao::state::{
+    DaoStats, Global, HistoryAction, PositionStats, RoundRecord, TimeWeightedBalance,
+};
+use auction_dao::types::BidResult;
+use cosmwasm_std::{Decimal256, Int256, SignedDecimal256, Storage, Uint128, Uint256};
+
+use crate::state::{DAO_STATS, GLOBAL_STATS, ROUND_HISTORY, USER_STATS};
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 3600;
+
+/* The totals are updated with the history entries, the balances when the accounts are saved.
+The yield is the realized rewards over the balance held each second, so a deposit only
+weighs for the time it stayed in the DAO */
+
+// Called for every history entry, see record_history
+pub fn record_stats(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    action: &HistoryAction,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let mut stats = USER_STATS.may_load(storage, user_addr)?.unwrap_or_default();
+
+    match action {
+        HistoryAction::Deposit | HistoryAction::TransferIn => stats.total_deposited += amount,
+        HistoryAction::Withdraw | HistoryAction::TransferOut => stats.total_withdrawn += amount,
+        HistoryAction::Harvest => stats.total_harvested += amount,
+        HistoryAction::Accrual => stats.total_earned += amount,
+        HistoryAction::Loss => stats.total_loss += amount,
+    }
+
+    USER_STATS.save(storage, user_addr, &stats)?;
+
+    // the transfers don't change the deposits of the DAO
+    let mut global_stats = GLOBAL_STATS.may_load(storage)?.unwrap_or_default();
+    match action {
+        HistoryAction::Deposit => global_stats.total_deposited += amount,
+        HistoryAction::Withdraw => global_stats.total_withdrawn += amount,
+        _ => return Ok(()),
+    }
+
+    GLOBAL_STATS.save(storage, &global_stats)?;
+
+    Ok(())
+}
+
+// Must run whenever the deposited amount changes
+pub fn record_balance(
+    storage: &mut dyn Storage,
+    user_addr: &str,
+    deposited: Uint128,
+    now: u64,
+) -> Result<(), ContractError> {
+    let mut stats = USER_STATS.may_load(storage, user_addr)?.unwrap_or_default();
+    update_balance(&mut stats.balance, deposited, now);
+
+    USER_STATS.save(storage, user_addr, &stats)?;
+
+    Ok(())
+}
+
+// Must run whenever the total supply changes
+pub fn record_supply(
+    storage: &mut dyn Storage,
+    global: &Global,
+    now: u64,
+) -> Result<(), ContractError> {
+    let mut global_stats = GLOBAL_STATS.may_load(storage)?.unwrap_or_default();
+    update_balance(&mut global_stats.balance, global.total_supply, now);
+
+    GLOBAL_STATS.save(storage, &global_stats)?;
+
+    Ok(())
+}
+
+fn update_balance(balance: &mut TimeWeightedBalance, new_balance: Uint128, now: u64) {
+    balance.balance_seconds = balance_seconds(balance, now);
+    balance.last_update_time = now;
+
+    if balance.first_deposit_time == 0 && !new_balance.is_zero() {
+        balance.first_deposit_time = now;
+    }
+
+    balance.last_balance = new_balance;
+}
+
+fn balance_seconds(balance: &TimeWeightedBalance, now: u64) -> Uint256 {
+    let held_secs = now.saturating_sub(balance.last_update_time);
+
+    balance.balance_seconds + Uint256::from_uint128(balance.last_balance) * Uint256::from(held_secs)
+}
+
+pub fn performance(
+    stats: &PositionStats,
+    realized_rewards: Uint128,
+    total_loss: Uint128,
+    now: u64,
+) -> Result<PerformanceResponse, ContractError> {
+    let balance_seconds = balance_seconds(&stats.balance, now);
+    let elapsed = now.saturating_sub(stats.balance.first_deposit_time);
+
+    let (average_balance, annualized_yield) = if balance_seconds.is_zero() || elapsed == 0 {
+        (Uint128::zero(), Decimal256::zero())
+    } else {
+        (
+            Uint128::try_from(balance_seconds / Uint256::from(elapsed))?,
+            Decimal256::from_ratio(
+                Uint256::from_uint128(realized_rewards) * Uint256::from(SECONDS_PER_YEAR),
+                balance_seconds,
+            ),
+        )
+    };
+
+    Ok(PerformanceResponse {
+        total_deposited: stats.total_deposited,
+        total_withdrawn: stats.total_withdrawn,
+        realized_rewards,
+        total_loss,
+        first_deposit_time: stats.balance.first_deposit_time,
+        average_balance,
+        annualized_yield,
+    })
+}
+
+/* Called when try_settle or try_clear_current_bid records the round. A round cleared after
+being outbid can be bid again, its new record replaces the cleared one in the history and
+in the totals, so the round is counted once */
+pub fn record_round_result(
+    storage: &mut dyn Storage,
+    record: &RoundRecord,
+) -> Result<(), ContractError> {
+    let mut dao_stats = DAO_STATS.may_load(storage)?.unwrap_or_default();
+
+    if let Some(previous) = ROUND_HISTORY.may_load(storage, record.round)? {
+        dao_stats.rounds_participated -= 1;
+        dao_stats.total_bid -= previous.bid_amount;
+        if previous.result == BidResult::Win {
+            dao_stats.rounds_won -= 1;
+            dao_stats.total_won_bid -= previous.bid_amount;
+        }
+    }
+
+    dao_stats.rounds_participated += 1;
+    dao_stats.total_bid += record.bid_amount;
+    if record.result == BidResult::Win {
+        dao_stats.rounds_won += 1;
+        dao_stats.total_won_bid += record.bid_amount;
+    }
+
+    DAO_STATS.save(storage, &dao_stats)?;
+    ROUND_HISTORY.save(storage, record.round, record)?;
+
+    Ok(())
+}
+
+// Called once the basket of a won round is sold
+pub fn record_round_settlement(
+    storage: &mut dyn Storage,
+    record: &RoundRecord,
+    proceeds: Uint128,
+) -> Result<(), ContractError> {
+    let mut dao_stats = DAO_STATS.may_load(storage)?.unwrap_or_default();
+
+    dao_stats.total_proceeds += proceeds;
+    dao_stats.total_profit += record.profit;
+    dao_stats.total_loss += record.loss;
+    dao_stats.total_keeper_rewards += record.keeper_reward;
+    dao_stats.largest_loss = dao_stats.largest_loss.max(record.loss);
+
+    DAO_STATS.save(storage, &dao_stats)?;
+
+    Ok(())
+}
+
+pub fn dao_stats(dao_stats: &DaoStats) -> StatsResponse {
+    let win_rate = if dao_stats.rounds_participated == 0 {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(dao_stats.rounds_won, dao_stats.rounds_participated)
+    };
+
+    let average_margin = if dao_stats.total_won_bid.is_zero() {
+        SignedDecimal256::zero()
+    } else {
+        SignedDecimal256::from_ratio(
+            Int256::from(dao_stats.total_profit.u128()) - Int256::from(dao_stats.total_loss.u128()),
+            Int256::from(dao_stats.total_won_bid.u128()),
+        )
+    };
+
+    StatsResponse {
+        rounds_participated: dao_stats.rounds_participated,
+        rounds_won: dao_stats.rounds_won,
+        win_rate,
+        total_bid: dao_stats.total_bid,
+        total_proceeds: dao_stats.total_proceeds,
+        total_profit: dao_stats.total_profit,
+        total_loss: dao_stats.total_loss,
+        total_keeper_rewards: dao_stats.total_keeper_rewards,
+        average_margin,
+        largest_loss: dao_stats.largest_loss,
+    }
+}
@@ -0,0 +1,241 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::Global;
+use cosmwasm_std::{
+    BankMsg, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, Storage, Uint128,
+};
+use injective_cosmwasm::{
+    create_burn_tokens_msg, create_mint_tokens_msg, create_new_denom_msg, InjectiveMsgWrapper,
+    InjectiveQueryWrapper,
+};
+
+use crate::{
+    admins::verify_sender_is_admin,
+    lp::{
+        accept_deposit, check_withdraw_time, decrease_supply, increase_supply, update_user_reward,
+    },
+    queue::queue_vault_deposit,
+    shutdown::{apply_exit_ratio, ensure_not_shutdown, exit_ratio},
+    state::{BID_ATTEMPT, CONFIG, GLOBAL, RECEIPT_DENOM, RECEIPT_SUBDENOM, VAULT_ACCOUNT},
+    stats::record_supply,
+    vesting::load_global,
+};
+
+/* The vault is a single pooled position inside the index accounting.
+Its rewards are compounded back into the principal, so the receipt denom
+exchange rate (vault assets / total shares) grows with the distributed profit */
+
+pub fn create_receipt_denom(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), &info.sender)?;
+
+    if RECEIPT_DENOM.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::ReceiptDenomAlreadyCreated {});
+    }
+
+    // the denom creation fee is paid from the funds sent along with this message
+    let denom = format!("factory/{}/{}", env.contract.address, RECEIPT_SUBDENOM);
+    RECEIPT_DENOM.save(deps.storage, &denom)?;
+
+    Ok(Response::new()
+        .add_message(create_new_denom_msg(
+            env.contract.address.to_string(),
+            RECEIPT_SUBDENOM.to_string(),
+        ))
+        .add_attribute("method", "create_receipt_denom")
+        .add_attribute("denom", denom))
+}
+
+// moves the vault pending reward into its principal
+pub fn compound_vault(storage: &mut dyn Storage, global: &mut Global) -> Result<(), ContractError> {
+    let mut vault = match VAULT_ACCOUNT.may_load(storage)? {
+        Some(vault) => vault,
+        None => return Ok(()),
+    };
+
+    update_user_reward(storage, &mut vault, global)?;
+
+    let reward = vault.pending_reward;
+    vault.pending_reward = Uint128::zero();
+    increase_supply(&mut vault, global, &reward);
+    global.rewards_settled += reward;
+
+    VAULT_ACCOUNT.save(storage, &vault)?;
+
+    Ok(())
+}
+
+pub fn vault_deposit(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_not_shutdown(&config)?;
+
+    if info.funds.len() != 1 || info.funds[0].denom != config.accepted_denom {
+        return Err(ContractError::InvalidDenom {});
+    }
+
+    let denom = RECEIPT_DENOM
+        .may_load(deps.storage)?
+        .ok_or(ContractError::ReceiptDenomNotCreated {})?;
+
+    let mut global = load_global(deps.storage, &env)?;
+    let (amount, refunded, msgs) = accept_deposit(
+        deps.as_ref(),
+        &config,
+        &global,
+        &info.sender,
+        info.funds[0].amount,
+    )?;
+
+    // Check if there is a bid, if so, the shares are minted once the queue is processed
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        if vault_depleted(deps.storage, &global)? {
+            return Err(ContractError::VaultDepleted {});
+        }
+
+        let response = queue_vault_deposit(deps, &info.sender, amount, global)?;
+        return Ok(response
+            .add_messages(msgs)
+            .add_attribute("accepted", amount)
+            .add_attribute("refunded", refunded));
+    }
+
+    let shares =
+        issue_shares(deps.storage, &mut global, amount)?.ok_or(ContractError::VaultDepleted {})?;
+
+    record_supply(deps.storage, &global, env.block.time.seconds())?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_message(create_mint_tokens_msg(
+            env.contract.address,
+            Coin {
+                denom,
+                amount: shares,
+            },
+            info.sender.to_string(),
+        ))
+        .add_attribute("method", "vault_deposit")
+        .add_attribute("owner", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("refunded", refunded)
+        .add_attribute("shares", shares))
+}
+
+/* Adds the amount to the vault principal and returns the shares to mint, none when
+losses wiped the vault out while shares are outstanding: minting at par would
+dilute the holders of a vault that may still earn from its pending rewards */
+pub(crate) fn issue_shares(
+    storage: &mut dyn Storage,
+    global: &mut Global,
+    amount: Uint128,
+) -> Result<Option<Uint128>, ContractError> {
+    compound_vault(storage, global)?;
+
+    let mut vault = VAULT_ACCOUNT.may_load(storage)?.unwrap_or_default();
+    update_user_reward(storage, &mut vault, global)?;
+
+    if !global.total_shares.is_zero() && vault.deposited.is_zero() {
+        return Ok(None);
+    }
+
+    let shares = if global.total_shares.is_zero() {
+        amount
+    } else {
+        amount.multiply_ratio(global.total_shares, vault.deposited)
+    };
+
+    increase_supply(&mut vault, global, &amount);
+    global.total_shares += shares;
+
+    VAULT_ACCOUNT.save(storage, &vault)?;
+
+    Ok(Some(shares))
+}
+
+pub(crate) fn vault_depleted(
+    storage: &dyn Storage,
+    global: &Global,
+) -> Result<bool, ContractError> {
+    let mut vault = VAULT_ACCOUNT.may_load(storage)?.unwrap_or_default();
+    // the vault has no referrer, the global is left as it is
+    update_user_reward(storage, &mut vault, &mut global.clone())?;
+
+    Ok(!global.total_shares.is_zero() && vault.deposited.is_zero())
+}
+
+pub fn vault_withdraw(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let denom = RECEIPT_DENOM
+        .may_load(deps.storage)?
+        .ok_or(ContractError::ReceiptDenomNotCreated {})?;
+
+    if info.funds.len() != 1 || info.funds[0].denom != denom {
+        return Err(ContractError::InvalidDenom {});
+    }
+
+    // Check if there is a bid
+    if let Some(_) = BID_ATTEMPT.may_load(deps.storage)? {
+        return Err(ContractError::ActiveBid {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    check_withdraw_time(deps.as_ref(), &env, &config)?;
+
+    let shares = info.funds[0].amount;
+    let mut global = load_global(deps.storage, &env)?;
+    let ratio = exit_ratio(deps.as_ref(), &env, &config, &global)?;
+
+    compound_vault(deps.storage, &mut global)?;
+
+    let mut vault = VAULT_ACCOUNT.may_load(deps.storage)?.unwrap_or_default();
+
+    if shares > global.total_shares {
+        return Err(ContractError::InsufficientFunds {});
+    }
+
+    let amount = shares.multiply_ratio(vault.deposited, global.total_shares);
+
+    decrease_supply(&mut vault, &mut global, &amount);
+    global.total_shares -= shares;
+
+    VAULT_ACCOUNT.save(deps.storage, &vault)?;
+    record_supply(deps.storage, &global, env.block.time.seconds())?;
+    GLOBAL.save(deps.storage, &global)?;
+
+    let mut msgs: Vec<CosmosMsg<_>> = vec![create_burn_tokens_msg(
+        env.contract.address,
+        Coin {
+            denom,
+            amount: shares,
+        },
+    )];
+
+    let payout = apply_exit_ratio(amount, ratio)?;
+    if payout > Uint128::zero() {
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: config.accepted_denom,
+                amount: payout,
+            }],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("method", "vault_withdraw")
+        .add_attribute("owner", info.sender)
+        .add_attribute("shares", shares)
+        .add_attribute("amount", amount))
+}
@@ -0,0 +1,61 @@
+use auction_dao::error::ContractError;
+use auction_dao::state::Global;
+use cosmwasm_std::{Env, Storage, Uint128};
+
+use crate::{lp::update_global_index, state::GLOBAL};
+
+/* With reward_vesting_secs set, the profit of a round is not added to the index at once,
+it is streamed linearly until global.vesting_end. A deposit arriving just before the
+settlement only earns the part vested while it stays. New profit restarts the period
+for the whole unvested amount */
+
+// Loads the global state with the profit vested until now distributed
+pub fn load_global(storage: &dyn Storage, env: &Env) -> Result<Global, ContractError> {
+    let mut global = GLOBAL.load(storage)?;
+    vest_profit(&mut global, env.block.time.seconds())?;
+
+    Ok(global)
+}
+
+pub fn vest_profit(global: &mut Global, now: u64) -> Result<(), ContractError> {
+    let vested = vested_profit(global, now);
+    global.last_vesting_time = global.last_vesting_time.max(now);
+
+    if vested.is_zero() {
+        return Ok(());
+    }
+
+    global.unvested_profit -= vested;
+
+    let max_tokens = global.vesting_max_tokens;
+    update_global_index(global, vested, max_tokens)
+}
+
+pub fn vested_profit(global: &Global, now: u64) -> Uint128 {
+    if global.unvested_profit.is_zero() || now <= global.last_vesting_time {
+        return Uint128::zero();
+    }
+
+    if now >= global.vesting_end {
+        return global.unvested_profit;
+    }
+
+    global.unvested_profit.multiply_ratio(
+        now - global.last_vesting_time,
+        global.vesting_end - global.last_vesting_time,
+    )
+}
+
+// The profit vested so far must be distributed first, see vest_profit
+pub fn add_vesting_profit(
+    global: &mut Global,
+    profit: Uint128,
+    vesting_secs: u64,
+    now: u64,
+    max_tokens: Uint128,
+) {
+    global.unvested_profit += profit;
+    global.vesting_end = now + vesting_secs;
+    global.last_vesting_time = now;
+    global.vesting_max_tokens = max_tokens;
+}
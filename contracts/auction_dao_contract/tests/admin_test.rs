@@ -2,7 +2,7 @@ mod util;
 mod tests {
 
     use crate::util::tests::{
-        create_realistic_hinj_inj_buy_orders_from_spreadsheet,
+        assert_approx_eq_uint128, create_realistic_hinj_inj_buy_orders_from_spreadsheet,
         create_realistic_hinj_inj_sell_orders_from_spreadsheet,
         create_realistic_inj_usdt_buy_orders_from_spreadsheet,
         create_realistic_inj_usdt_sell_orders_from_spreadsheet, init, init_contract_inj,
@@ -11,14 +11,17 @@ mod tests {
     };
     use auction_dao::{
         error::ContractError,
-        msg::{ExecuteMsg, QueryMsg},
-        state::Global,
+        msg::{ExecuteMsg, QueryMsg, ShutdownStatusResponse},
+        state::{Global, UserAccount},
     };
 
-    use cosmwasm_std::{Coin, Uint128};
-    use injective_std::types::cosmos::{
-        bank::v1beta1::{MsgSend, QueryBalanceRequest},
-        base::v1beta1::Coin as BaseCoin,
+    use cosmwasm_std::{Coin, Decimal256, Uint128};
+    use injective_std::types::{
+        cosmos::{
+            bank::v1beta1::{MsgSend, QueryBalanceRequest},
+            base::v1beta1::Coin as BaseCoin,
+        },
+        injective::auction::v1beta1::QueryCurrentAuctionBasketResponse,
     };
     use injective_test_tube::{Bank, Exchange, InjectiveTestApp, Wasm};
     use test_tube_inj::{Account, Module};
@@ -158,7 +161,11 @@ mod tests {
         // initial deposit
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(1 * ONE_18, "inj")],
             admin,
         )
@@ -269,7 +276,11 @@ mod tests {
         // initial deposit
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(1 * ONE_18, INJ)],
             admin,
         )
@@ -326,4 +337,319 @@ mod tests {
             "accumulated profit not equal"
         );
     }
+
+    #[test]
+    fn shutdown_stops_deposits_and_lets_users_exit() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![BaseCoin {
+                    amount: (2 * deposited_amount).to_string(),
+                    denom: INJ.to_string(),
+                }],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, INJ)],
+            user,
+        )
+        .unwrap();
+
+        let r = wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Shutdown {}, &[], user);
+        assert!(r.is_err(), "Expected unauthorized error");
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains(ContractError::Unauthorized {}.to_string().as_str()));
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Shutdown {}, &[], admin)
+            .unwrap();
+
+        let r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, INJ)],
+            user,
+        );
+        assert!(r.is_err(), "Expected shutdown error");
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains(ContractError::Shutdown {}.to_string().as_str()));
+
+        let status = wasm
+            .query::<QueryMsg, ShutdownStatusResponse>(&contract_addr, &QueryMsg::ShutdownStatus {})
+            .unwrap();
+        assert!(status.shutdown);
+        assert_eq!(status.exit_ratio, Some(Decimal256::one()));
+        assert!(status.unsold_assets.is_empty());
+
+        let balance_before = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: INJ.to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: deposited_amount.into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let balance_after = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: INJ.to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        // the withdraw fee is paid in inj as well
+        assert_approx_eq_uint128(
+            Uint128::new(balance_after - balance_before),
+            Uint128::new(deposited_amount),
+            10000,
+        );
+    }
+
+    #[test]
+    fn shutdown_releases_locked_positions() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![BaseCoin {
+                    amount: (2 * deposited_amount).to_string(),
+                    denom: INJ.to_string(),
+                }],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: Some(12),
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, INJ)],
+            user,
+        )
+        .unwrap();
+
+        let global = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(global.total_lock_bonus, Uint128::new(deposited_amount / 2));
+
+        let r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: deposited_amount.into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        );
+        assert!(r.is_err(), "Expected position locked error");
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains("Position is locked until round"));
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Shutdown {}, &[], admin)
+            .unwrap();
+
+        // the lock doesn't hold the position after a shutdown
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: deposited_amount.into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        // the bonus ended with the lock
+        let global = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(global.total_supply, Uint128::zero());
+        assert_eq!(global.total_lock_bonus, Uint128::zero());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::zero());
+        assert_eq!(user_account.locked, Uint128::zero());
+        assert_eq!(user_account.lock_bonus, Uint128::zero());
+    }
+
+    #[test]
+    fn shutdown_exits_wait_for_the_active_bid() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(10000000 * ONE_18, INJ)], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![BaseCoin {
+                    amount: deposited_amount.to_string(),
+                    denom: INJ.to_string(),
+                }],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, INJ)],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Shutdown {}, &[], admin)
+            .unwrap();
+
+        // the bid amount is out of the balance, paying now would shortchange the last ones out
+        let status = wasm
+            .query::<QueryMsg, ShutdownStatusResponse>(&contract_addr, &QueryMsg::ShutdownStatus {})
+            .unwrap();
+        assert!(status.shutdown);
+        assert_eq!(status.exit_ratio, None);
+
+        let r = wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user);
+        assert!(r.is_err(), "Expected active bid error");
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains(ContractError::ActiveBid {}.to_string().as_str()));
+
+        app.increase_time(10);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::TrySettle {}, &[], admin)
+            .unwrap();
+
+        let status = wasm
+            .query::<QueryMsg, ShutdownStatusResponse>(&contract_addr, &QueryMsg::ShutdownStatus {})
+            .unwrap();
+        assert!(status.exit_ratio.is_some());
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+    }
 }
@@ -5,13 +5,14 @@ mod tests {
 
     use crate::util::tests::{
         assert_approx_eq_uint128, create_realistic_inj_usdt_buy_orders_from_spreadsheet,
-        create_realistic_inj_usdt_sell_orders_from_spreadsheet, init, init_contract_inj,
-        init_router_contract_inj, launch_realistic_inj_usdt_spot_market, AUCTION_VAULT_ADDRESS,
-        ONE_18, ONE_6,
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet, default_lock_tiers, init,
+        init_contract_inj, init_router_contract_inj, launch_realistic_inj_usdt_spot_market,
+        AUCTION_VAULT_ADDRESS, ONE_18, ONE_6,
     };
-    use auction_dao::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use auction_dao::msg::{BasketValuationResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+    use auction_dao::state::{BidStrategy, ValuationPolicy};
 
-    use cosmwasm_std::{Coin, Uint128};
+    use cosmwasm_std::{from_json, Coin, Uint128};
     use injective_math::FPDecimal;
     use injective_std::types::{
         cosmos::bank::v1beta1::MsgSend,
@@ -68,6 +69,11 @@ mod tests {
                     withdraw_time_buffer: 18000,
                     max_inj_offset_bps: Uint128::from(15900u128),
                     winning_bidder_reward_bps: Uint128::from(1000u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
                 },
             },
             &[Coin::new(Uint128::one(), "inj")],
@@ -87,6 +93,11 @@ mod tests {
                     withdraw_time_buffer: 18000,
                     max_inj_offset_bps: Uint128::from(14000u128),
                     winning_bidder_reward_bps: Uint128::from(500u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
                 },
             },
             &[Coin::new(Uint128::one(), "inj")],
@@ -106,6 +117,11 @@ mod tests {
                     withdraw_time_buffer: 18000,
                     max_inj_offset_bps: Uint128::from(15000u128),
                     winning_bidder_reward_bps: Uint128::from(1000u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
                 },
             },
             &[Coin::new(Uint128::one(), "inj")],
@@ -113,6 +129,29 @@ mod tests {
         );
 
         assert!(try_config_update_wrong_fields.is_err());
+
+        // the message sent by update_config.sh, without the fields added later
+        let script_msg = from_json::<ExecuteMsg>(format!(
+            r#"{{
+                "update_config": {{
+                    "new_config": {{
+                        "admin": "{}",
+                        "accepted_denom": "inj",
+                        "swap_router": "{}",
+                        "bid_time_buffer": 5,
+                        "withdraw_time_buffer": 7200,
+                        "max_inj_offset_bps": "12500",
+                        "winning_bidder_reward_bps": "500"
+                    }}
+                }}
+            }}"#,
+            admin.address(),
+            router_contract_add
+        ))
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &script_msg, &[], admin)
+            .unwrap();
     }
 
     #[test]
@@ -440,4 +479,118 @@ mod tests {
             "set route should have failed"
         );
     }
+
+    #[test]
+    fn basket_valuation_follows_the_policy() {
+        let app = init();
+        let admin = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(1000000000000000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap()[0];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let exchange = Exchange::new(&app);
+        let bank = Bank::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_buy_orders_from_spreadsheet(&exchange, &market_id, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetRoute {
+                source_denom: "inj".to_string(),
+                target_denom: "usdt".to_string(),
+                market_id: market_id.clone(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(10000 * ONE_6, "usdt").into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let valuation = wasm
+            .query::<QueryMsg, BasketValuationResponse>(
+                &contract_addr,
+                &QueryMsg::BasketValuation {},
+            )
+            .unwrap();
+
+        assert_eq!(valuation.policy, ValuationPolicy::Exchange);
+        assert_eq!(valuation.value, Some(valuation.exchange_value));
+
+        let max_tokens = wasm
+            .query::<QueryMsg, Uint128>(&contract_addr, &QueryMsg::MaxAllowedTokensToDeposit {})
+            .unwrap();
+        assert_eq!(
+            max_tokens,
+            valuation
+                .exchange_value
+                .multiply_ratio(15000u128, 10000u128)
+        );
+
+        let lowest_value = valuation.router_value.min(valuation.exchange_value);
+        let policies = [
+            (ValuationPolicy::Router, Some(valuation.router_value)),
+            (ValuationPolicy::Min, Some(lowest_value)),
+            (
+                ValuationPolicy::RejectOnDivergence {
+                    max_divergence_bps: valuation.divergence_bps,
+                },
+                Some(lowest_value),
+            ),
+        ];
+
+        for (valuation_policy, expected_value) in policies {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::UpdateConfig {
+                    new_config: InstantiateMsg {
+                        admin: admin.address(),
+                        accepted_denom: "inj".to_string(),
+                        swap_router: router_contract_add.to_string(),
+                        bid_time_buffer: 5,
+                        withdraw_time_buffer: 18000,
+                        max_inj_offset_bps: Uint128::from(15000u128),
+                        winning_bidder_reward_bps: Uint128::from(500u128),
+                        lock_tiers: default_lock_tiers(),
+                        referral_reward_bps: Uint128::zero(),
+                        reward_vesting_secs: 0,
+                        bid_strategy: BidStrategy::default(),
+                        valuation_policy,
+                    },
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+
+            let valuation = wasm
+                .query::<QueryMsg, BasketValuationResponse>(
+                    &contract_addr,
+                    &QueryMsg::BasketValuation {},
+                )
+                .unwrap();
+
+            assert_eq!(valuation.value, expected_value);
+        }
+    }
 }
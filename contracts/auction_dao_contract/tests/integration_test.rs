@@ -6,21 +6,26 @@ mod tests {
         assert_approx_eq_uint128, create_realistic_hinj_inj_buy_orders_from_spreadsheet,
         create_realistic_hinj_inj_sell_orders_from_spreadsheet,
         create_realistic_inj_usdt_buy_orders_from_spreadsheet,
-        create_realistic_inj_usdt_sell_orders_from_spreadsheet, init, init_contract_inj,
-        init_router_contract_inj, launch_realistic_hinj_inj_spot_market,
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet, default_lock_tiers, init,
+        init_contract_inj, init_router_contract_inj, launch_realistic_hinj_inj_spot_market,
         launch_realistic_inj_usdt_spot_market, AUCTION_VAULT_ADDRESS, HINJ, INJ, ONE_18, ONE_6,
         USDT,
     };
     use auction_dao::{
-        msg::{ExecuteMsg, QueryMsg},
-        state::Global,
+        error::ContractError,
+        msg::{
+            BidCapacityResponse, ExecuteMsg, InstantiateMsg, QueryMsg, RoundsResponse,
+            StatsResponse,
+        },
+        state::{BidStrategy, Global, UserAccount, ValuationPolicy},
+        types::BidResult,
     };
 
     use injective_std::types::cosmos::{
         bank::v1beta1::QueryBalanceRequest, base::v1beta1::Coin as BidCoin,
     };
 
-    use cosmwasm_std::{Coin, Uint128};
+    use cosmwasm_std::{Coin, Decimal256, SignedDecimal256, Uint128};
     use injective_std::types::{
         cosmos::bank::v1beta1::MsgSend,
         injective::auction::v1beta1::{MsgBid, QueryCurrentAuctionBasketResponse},
@@ -85,7 +90,11 @@ mod tests {
         // Users cant deposit to the contract - auction basket empty
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(
                 inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
                 INJ,
@@ -119,7 +128,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(
                 inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
                 INJ,
@@ -131,7 +144,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(
                 inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
                 INJ,
@@ -438,10 +455,42 @@ mod tests {
             .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
             .unwrap();
 
+        // the settled round is kept once the bid attempt is removed
+        let rounds = wasm
+            .query::<QueryMsg, RoundsResponse>(
+                &contract_addr,
+                &QueryMsg::Rounds {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(rounds.rounds.len(), 1);
+        assert_eq!(rounds.rounds[0].result, BidResult::Win);
+        assert_eq!(rounds.rounds[0].winner, contract_addr);
+        assert_eq!(rounds.rounds[0].index, global.index);
+        assert!(!rounds.rounds[0].proceeds.is_empty());
+        assert!(rounds.rounds[0].profit > Uint128::zero());
+
+        let stats = wasm
+            .query::<QueryMsg, StatsResponse>(&contract_addr, &QueryMsg::Stats {})
+            .unwrap();
+
+        assert_eq!(stats.rounds_participated, 1);
+        assert_eq!(stats.rounds_won, 1);
+        assert_eq!(stats.win_rate, Decimal256::one());
+        assert_eq!(stats.total_bid, rounds.rounds[0].bid_amount);
+        assert_eq!(stats.total_profit, rounds.rounds[0].profit);
+        assert_eq!(stats.total_keeper_rewards, rounds.rounds[0].keeper_reward);
+        assert_eq!(stats.largest_loss, Uint128::zero());
+        assert!(stats.average_margin > SignedDecimal256::zero());
+
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
+                recipient: None,
             },
             &[],
             user1,
@@ -452,6 +501,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
+                recipient: None,
             },
             &[],
             user2,
@@ -615,7 +665,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(
                 inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
                 INJ,
@@ -627,7 +681,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(
                 inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
                 INJ,
@@ -737,6 +795,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
+                recipient: None,
             },
             &[],
             user1,
@@ -747,6 +806,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
+                recipient: None,
             },
             &[],
             user2,
@@ -800,4 +860,524 @@ mod tests {
             50,
         );
     }
+
+    #[test]
+    fn bid_strategy_rejects_unprofitable_bids() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, INJ),
+                    Coin::new(10000000 * ONE_6, USDT),
+                ],
+                2,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let exchange = Exchange::new(&app);
+        let auction = Auction::new(&app);
+        let bank = Bank::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_buy_orders_from_spreadsheet(&exchange, &market_id, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let inj_amount_to_basket = Coin::new(10000 * ONE_18, INJ);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![inj_amount_to_basket.clone().into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(
+                inj_amount_to_basket.amount.multiply_ratio(1u128, 2u128),
+                INJ,
+            )],
+            user,
+        )
+        .unwrap();
+
+        // someone else bids the basket value, the basket is then doubled to make it worth it
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: inj_amount_to_basket.amount.to_string(),
+                        denom: INJ.to_string(),
+                    }),
+                    round: 0,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![inj_amount_to_basket.clone().into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        let strategies = [
+            (
+                BidStrategy {
+                    max_bid: Uint128::from(ONE_18),
+                    ..BidStrategy::default()
+                },
+                "is above the max bid",
+            ),
+            (
+                BidStrategy {
+                    max_supply_bps: Uint128::from(100u128),
+                    ..BidStrategy::default()
+                },
+                "allowed for the total supply",
+            ),
+            (
+                // the basket is worth twice the bid, a 100% margin is out of reach after fees
+                BidStrategy {
+                    min_margin_bps: Uint128::from(10000u128),
+                    taker_fee_bps: Uint128::from(10u128),
+                    ..BidStrategy::default()
+                },
+                "is required",
+            ),
+        ];
+
+        for (bid_strategy, reason) in strategies {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::UpdateConfig {
+                    new_config: InstantiateMsg {
+                        admin: admin.address(),
+                        accepted_denom: INJ.to_string(),
+                        swap_router: router_contract_add.to_string(),
+                        bid_time_buffer: 5,
+                        withdraw_time_buffer: 18000,
+                        max_inj_offset_bps: Uint128::from(15000u128),
+                        winning_bidder_reward_bps: Uint128::from(500u128),
+                        lock_tiers: default_lock_tiers(),
+                        referral_reward_bps: Uint128::from(1000u128),
+                        reward_vesting_secs: 0,
+                        bid_strategy,
+                        valuation_policy: ValuationPolicy::Exchange,
+                    },
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+
+            let try_bid_response = wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::TryBid {
+                    round: current_auction_round,
+                },
+                &[],
+                admin,
+            );
+
+            assert!(
+                try_bid_response.unwrap_err().to_string().contains(reason),
+                "incorrect rejection reason"
+            );
+        }
+    }
+
+    #[test]
+    fn bid_is_limited_by_the_pooled_funds() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, INJ),
+                    Coin::new(10000000 * ONE_6, USDT),
+                ],
+                2,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let exchange = Exchange::new(&app);
+        let auction = Auction::new(&app);
+        let bank = Bank::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_buy_orders_from_spreadsheet(&exchange, &market_id, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let inj_amount_to_basket = Coin::new(10000 * ONE_18, INJ);
+        let deposit_amount = Uint128::from(1000 * ONE_18);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![inj_amount_to_basket.clone().into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposit_amount, INJ)],
+            user,
+        )
+        .unwrap();
+
+        // the funds sent outside of a deposit are not pooled
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin::new(deposit_amount, INJ).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let bid_capacity = wasm
+            .query::<QueryMsg, BidCapacityResponse>(&contract_addr, &QueryMsg::BidCapacity {})
+            .unwrap();
+
+        assert_eq!(bid_capacity.balance, deposit_amount + deposit_amount);
+        assert_eq!(bid_capacity.pooled_funds, deposit_amount);
+        assert_eq!(bid_capacity.capacity, deposit_amount);
+
+        // someone else bids the basket value, the basket is then doubled to make it worth it
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: inj_amount_to_basket.amount.to_string(),
+                        denom: INJ.to_string(),
+                    }),
+                    round: 0,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![inj_amount_to_basket.clone().into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        let try_bid_response = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        );
+
+        assert!(
+            try_bid_response
+                .unwrap_err()
+                .to_string()
+                .contains("is above the bid capacity"),
+            "incorrect rejection reason"
+        );
+    }
+
+    #[test]
+    fn basket_sold_below_the_bid_reduces_the_principal() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, INJ),
+                    Coin::new(10000000 * ONE_6, USDT),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(10005 * ONE_18, INJ)], 2)
+            .unwrap();
+        let user1 = &accounts[0];
+        let user2 = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let exchange = Exchange::new(&app);
+        let auction = Auction::new(&app);
+        let bank = Bank::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_buy_orders_from_spreadsheet(&exchange, &market_id, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let inj_amount_to_basket = Coin::new(10000 * ONE_18, INJ);
+        let usdt_amount_to_basket = Coin::new(200000 * ONE_6, USDT);
+        let deposit_amount = Uint128::from(7500 * ONE_18);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetRoute {
+                source_denom: INJ.to_string(),
+                target_denom: USDT.to_string(),
+                market_id,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![
+                    inj_amount_to_basket.clone().into(),
+                    usdt_amount_to_basket.clone().into(),
+                ],
+            },
+            admin,
+        )
+        .unwrap();
+
+        for user in [user1, user2] {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(deposit_amount, INJ)],
+                user,
+            )
+            .unwrap();
+        }
+
+        // the contract has to bid above the inj part of the basket
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: inj_amount_to_basket.amount.to_string(),
+                        denom: INJ.to_string(),
+                    }),
+                    round: 0,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // without a route the usdt is not sold, only the inj of the basket is received
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::DeleteRoute {
+                source_denom: INJ.to_string(),
+                target_denom: USDT.to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        app.increase_time(10);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::TrySettle {}, &[], admin)
+            .unwrap();
+
+        let rounds = wasm
+            .query::<QueryMsg, RoundsResponse>(
+                &contract_addr,
+                &QueryMsg::Rounds {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(rounds.rounds[0].result, BidResult::Win);
+
+        let expected_loss = rounds.rounds[0].bid_amount - inj_amount_to_basket.amount;
+        assert!(expected_loss > Uint128::zero());
+        assert_eq!(rounds.rounds[0].loss, expected_loss);
+
+        let round_loss = wasm
+            .query::<QueryMsg, Uint128>(
+                &contract_addr,
+                &QueryMsg::RoundLoss {
+                    round: current_auction_round,
+                },
+            )
+            .unwrap();
+        assert_eq!(round_loss, expected_loss);
+
+        let global = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(
+            global.total_supply,
+            deposit_amount + deposit_amount - round_loss
+        );
+        assert_eq!(global.accumulated_loss, round_loss);
+
+        // both users deposited the same amount, so they share the loss equally
+        let mut total_loss = Uint128::zero();
+        let mut total_principal = Uint128::zero();
+        for user in [user1, user2] {
+            let user_account = wasm
+                .query::<QueryMsg, UserAccount>(
+                    &contract_addr,
+                    &QueryMsg::User {
+                        address: user.address(),
+                    },
+                )
+                .unwrap();
+
+            assert_approx_eq_uint128(
+                user_account.total_loss,
+                round_loss.multiply_ratio(1u128, 2u128),
+                1,
+            );
+            assert_eq!(
+                user_account.deposited,
+                deposit_amount - user_account.total_loss
+            );
+            assert_eq!(user_account.pending_reward, Uint128::zero());
+
+            total_loss += user_account.total_loss;
+            total_principal += user_account.deposited;
+
+            // the amount deposited before the loss is no longer there to withdraw
+            let r = wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Withdraw {
+                    amount: deposit_amount,
+                    recipient: None,
+                },
+                &[],
+                user,
+            );
+            assert!(r.is_err(), "Expected insufficient funds error");
+            assert!(r
+                .unwrap_err()
+                .to_string()
+                .contains(ContractError::InsufficientFunds {}.to_string().as_str()));
+
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Withdraw {
+                    amount: user_account.deposited,
+                    recipient: None,
+                },
+                &[],
+                user,
+            )
+            .unwrap();
+        }
+
+        // the principals are rounded down, only that dust is left in the supply
+        assert!(round_loss <= total_loss && total_loss <= round_loss + Uint128::new(2));
+        assert!(total_principal <= global.total_supply);
+
+        let global = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert!(global.total_supply <= Uint128::new(2));
+    }
 }
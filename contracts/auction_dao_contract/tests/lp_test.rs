@@ -5,17 +5,26 @@ mod tests {
     use std::str::FromStr;
 
     use crate::util::tests::{
-        assert_approx_eq_uint128, create_realistic_inj_usdt_sell_orders_from_spreadsheet, init,
+        assert_approx_eq_uint128, create_realistic_inj_usdt_buy_orders_from_spreadsheet,
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet, default_lock_tiers, init,
         init_contract_inj, init_router_contract_inj, launch_realistic_inj_usdt_spot_market,
         AUCTION_VAULT_ADDRESS, ONE_18, ONE_6,
     };
     use auction_dao::{
         error::ContractError,
-        msg::{ExecuteMsg, InstantiateMsg, QueryMsg},
-        state::{Global, UserAccount},
+        msg::{
+            EligibilityResponse, ExecuteMsg, InstantiateMsg, PerformanceResponse, QueryMsg,
+            ReferralsResponse, RoundsResponse, SolvencyResponse, StatsResponse,
+            UserHistoryResponse, UserRewardsResponse, UsersResponse, VestingResponse,
+        },
+        state::{
+            BidStrategy, Global, HistoryAction, QueuedAction, UserAccount, ValuationPolicy,
+            WithdrawRequest,
+        },
+        types::BidResult,
     };
 
-    use cosmwasm_std::{Coin, Uint128};
+    use cosmwasm_std::{Coin, Decimal256, Uint128};
     use injective_std::types::{
         cosmos::bank::v1beta1::{MsgSend, QueryBalanceRequest},
         cosmos::base::v1beta1::Coin as BidCoin,
@@ -63,7 +72,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(10 * ONE_18, "not_inj")],
             user,
         );
@@ -116,7 +129,11 @@ mod tests {
 
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         );
@@ -144,13 +161,60 @@ mod tests {
 
         assert_eq!(user_account.deposited, Uint128::new(deposited_amount));
 
-        /* We try deposit again 6 more inj; should fail as baske value 10inj and we
-        commitng 16 with a max_inj_offset of 150 (50%)
+        /* We try deposit again 6 more inj; baske value 10inj and we would be
+        commitng 16 with a max_inj_offset of 150 (50%), so only 5 inj are accepted
          */
+        let deposit_response = wasm
+            .execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(6 * ONE_18, "inj")],
+                user,
+            )
+            .unwrap();
+
+        let wasm_event = deposit_response
+            .events
+            .iter()
+            .find(|e| e.ty == "wasm")
+            .unwrap();
+        let accepted = wasm_event
+            .attributes
+            .iter()
+            .find(|a| a.key == "accepted")
+            .unwrap();
+        let refunded = wasm_event
+            .attributes
+            .iter()
+            .find(|a| a.key == "refunded")
+            .unwrap();
+        assert_eq!(accepted.value, (5 * ONE_18).to_string());
+        assert_eq!(refunded.value, ONE_18.to_string());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(user_account.deposited, Uint128::new(15 * ONE_18));
+
+        // The max tokens are reached, nothing can be accepted
         let deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
-            &[Coin::new(6 * ONE_18, "inj")],
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(ONE_18, "inj")],
             user,
         );
 
@@ -162,15 +226,6 @@ mod tests {
                 .contains("Cannot exceed max tokens"),
             "incorrect query result error message"
         );
-
-        // If we try again with 5 inj should work
-        let deposit_response = wasm.execute::<ExecuteMsg>(
-            &contract_addr,
-            &ExecuteMsg::Deposit {},
-            &[Coin::new(5 * ONE_18, "inj")],
-            user,
-        );
-        assert!(deposit_response.is_ok());
     }
 
     #[test]
@@ -204,7 +259,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -212,7 +271,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -254,6 +317,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user,
@@ -264,6 +328,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -312,7 +377,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -320,7 +389,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -345,6 +418,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user,
@@ -366,6 +440,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user,
@@ -376,6 +451,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -437,7 +513,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -445,7 +525,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -565,7 +649,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -573,7 +661,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -613,6 +705,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user,
@@ -623,6 +716,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -694,7 +788,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -702,7 +800,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -744,7 +846,11 @@ mod tests {
         // it shouldn't have any effect on the previous profit distribution
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -754,6 +860,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (2 * deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
@@ -790,6 +897,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -866,7 +974,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -874,7 +986,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -919,7 +1035,11 @@ mod tests {
         // it shouldn't have any effect on the previous profit distribution
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -932,6 +1052,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (2 * deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
@@ -955,6 +1076,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -1044,7 +1166,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -1052,7 +1178,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -1126,6 +1256,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
@@ -1149,6 +1280,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: deposited_amount.into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -1188,6 +1320,136 @@ mod tests {
         assert_eq!(r.balance.unwrap().amount, "0".to_string());
     }
 
+    #[test]
+    fn rounding_dust_is_counted_once_over_several_rounds() {
+        let app = init();
+        let admin_initial_inj = 10000000 * ONE_18;
+        let initial_inj = 100 * ONE_18;
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(admin_initial_inj, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let users = &app
+            .init_accounts(&[Coin::new(initial_inj, "inj")], 3)
+            .unwrap();
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(100 * ONE_18, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let exchange = Exchange::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        // uneven deposits so that neither the index nor the user rewards divide evenly
+        let deposits = [7 * ONE_18 + 1, 11 * ONE_18 + 3, 13 * ONE_18 + 7];
+        for (user, deposit) in users.iter().zip(deposits) {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(deposit, "inj")],
+                user,
+            )
+            .unwrap();
+        }
+
+        let supply: u128 = deposits.iter().sum();
+        let rounds = 3u128;
+        let usdt_profit = ONE_6 * 3333;
+
+        for round in 0..rounds as usize {
+            bank.send(
+                MsgSend {
+                    from_address: admin.address(),
+                    to_address: contract_addr.clone(),
+                    amount: vec![Coin {
+                        amount: usdt_profit.into(),
+                        denom: "usdt".to_string(),
+                    }
+                    .into()],
+                },
+                &admin,
+            )
+            .unwrap();
+
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::ManualExchangeSwap {
+                    amount: usdt_profit.into(),
+                    market_id: market_id.clone(),
+                    asset: "usdt".to_string(),
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+
+            // a different user claims after each round
+            wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], &users[round])
+                .unwrap();
+
+            let state = wasm
+                .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+                .unwrap();
+            let dust = wasm
+                .query::<QueryMsg, Uint128>(&contract_addr, &QueryMsg::AccumulatedDust {})
+                .unwrap();
+
+            // the carried remainder was counted when it was left over, and is not counted again
+            assert!(state.profit_to_distribute <= dust);
+            // each distribution leaves less than one unit per whole token of supply
+            assert!(dust.u128() <= (round as u128 + 1) * (supply / ONE_18 + 1));
+        }
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+
+        let mut owed = Uint128::zero();
+        for user in users {
+            let rewards = wasm
+                .query::<QueryMsg, UserRewardsResponse>(
+                    &contract_addr,
+                    &QueryMsg::UserRewards {
+                        address: user.address(),
+                    },
+                )
+                .unwrap();
+            owed += rewards.total_harvested + rewards.pending_reward;
+        }
+
+        // the users are never owed more than distributed, and lose less than one unit each
+        // to the reward fractions carried to their next update
+        assert!(owed <= state.accumulated_profit);
+        assert!(state.accumulated_profit - owed < Uint128::from(deposits.len() as u128));
+    }
+
     #[test]
     fn deposit_and_withdraw_after_bid_attempt() {
         let app = init();
@@ -1233,7 +1495,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -1241,7 +1507,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -1271,25 +1541,58 @@ mod tests {
         );
         assert!(try_bid_response.is_ok());
 
-        // withdrawing should fail if there is an active bid
+        // withdrawing is queued if there is an active bid
         let try_withdraw_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
         );
-        assert!(try_withdraw_response.is_err());
+        assert!(try_withdraw_response.is_ok());
 
-        // depositing should fail if there is an active bid
+        // depositing is queued if there is an active bid
         let try_deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         );
-        assert!(try_deposit_response.is_err());
+        assert!(try_deposit_response.is_ok());
+
+        let queued_action = wasm
+            .query::<QueryMsg, QueuedAction>(
+                &contract_addr,
+                &QueryMsg::QueuedActions {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(queued_action.deposit, Uint128::new(deposited_amount));
+        assert_eq!(queued_action.withdraw, Uint128::new(deposited_amount));
+
+        // the queued deposit doesn't earn the profit of the active round
+        let eligibility = wasm
+            .query::<QueryMsg, EligibilityResponse>(
+                &contract_addr,
+                &QueryMsg::Eligibility {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(eligibility.eligible, Uint128::new(deposited_amount));
+        assert_eq!(eligibility.ineligible, Uint128::new(deposited_amount));
+        assert_eq!(eligibility.round, Some(current_auction_round));
+        assert_eq!(
+            eligibility.eligible_supply,
+            Some(Uint128::new(2 * deposited_amount))
+        );
 
         // current bid is active and contract is highest bidder
         // try clear bid should fail therefore
@@ -1326,10 +1629,57 @@ mod tests {
         );
         assert!(try_clear_bid_response.is_ok());
 
+        // the cleared round counts as lost in the stats
+        let rounds = wasm
+            .query::<QueryMsg, RoundsResponse>(
+                &contract_addr,
+                &QueryMsg::Rounds {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(rounds.rounds.len(), 1);
+        assert_eq!(rounds.rounds[0].result, BidResult::Loss);
+        assert_eq!(rounds.rounds[0].winner, admin.address());
+        assert_eq!(rounds.rounds[0].winning_bid, Uint128::new(ONE_18));
+
+        let stats = wasm
+            .query::<QueryMsg, StatsResponse>(&contract_addr, &QueryMsg::Stats {})
+            .unwrap();
+        assert_eq!(stats.rounds_participated, 1);
+        assert_eq!(stats.rounds_won, 0);
+
+        // clearing the bid processed the queue
+        let queued_action = wasm
+            .query::<QueryMsg, QueuedAction>(
+                &contract_addr,
+                &QueryMsg::QueuedActions {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(queued_action.deposit, Uint128::zero());
+        assert_eq!(queued_action.withdraw, Uint128::zero());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(deposited_amount));
+
         //users can deposit
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -1347,6 +1697,11 @@ mod tests {
                     withdraw_time_buffer: 0,
                     max_inj_offset_bps: Uint128::from(15000u128),
                     winning_bidder_reward_bps: Uint128::from(500u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
                 },
             },
             &[],
@@ -1358,6 +1713,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (2 * deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
@@ -1368,6 +1724,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -1419,7 +1776,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -1427,7 +1788,11 @@ mod tests {
 
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user2,
         )
@@ -1457,25 +1822,41 @@ mod tests {
         );
         assert!(try_bid_response.is_ok());
 
-        // withdrawing should fail if there is an active bid
+        // withdrawing is queued if there is an active bid
         let try_withdraw_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user,
         );
-        assert!(try_withdraw_response.is_err());
+        assert!(try_withdraw_response.is_ok());
 
-        // depositing should fail if there is an active bid
+        // depositing is queued if there is an active bid
         let try_deposit_response = wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         );
-        assert!(try_deposit_response.is_err());
+        assert!(try_deposit_response.is_ok());
+
+        let queued_action = wasm
+            .query::<QueryMsg, QueuedAction>(
+                &contract_addr,
+                &QueryMsg::QueuedActions {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(queued_action.deposit, Uint128::new(deposited_amount));
+        assert_eq!(queued_action.withdraw, Uint128::new(deposited_amount));
 
         // current bid is active and contract is highest bidder
         // try clear bid should fail therefore
@@ -1510,6 +1891,14 @@ mod tests {
         );
         assert!(try_settle_response.is_ok());
 
+        // settling the bid processed the queue
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.queued_deposits, Uint128::zero());
+        assert_eq!(state.queued_withdrawals, Uint128::zero());
+        assert_eq!(state.total_supply, Uint128::new(2 * deposited_amount));
+
         // send something to the new auction again
         bank.send(
             MsgSend {
@@ -1524,7 +1913,11 @@ mod tests {
         //users can deposit
         wasm.execute::<ExecuteMsg>(
             &contract_addr,
-            &ExecuteMsg::Deposit {},
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
             &[Coin::new(deposited_amount, "inj")],
             user,
         )
@@ -1534,6 +1927,7 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (deposited_amount).into(),
+                recipient: None,
             },
             &[],
             user2,
@@ -1544,10 +1938,2598 @@ mod tests {
             &contract_addr,
             &ExecuteMsg::Withdraw {
                 amount: (2 * deposited_amount).into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn auto_compound_rewards_up_to_max_tokens() {
+        let app = init();
+        let admin_initial_inj = 10000000 * ONE_18;
+        let initial_inj = 100 * ONE_18;
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(admin_initial_inj, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(initial_inj, "inj")], 2)
+            .unwrap();
+        let user = &accounts[0];
+        let user2 = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        // 30 inj in the basket allow 45 inj to be deposited
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(3 * deposited_amount, "inj".to_string()).into()],
             },
+            admin,
+        )
+        .unwrap();
+
+        let exchange = Exchange::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        for u in [user, user2] {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(deposited_amount, "inj")],
+                u,
+            )
+            .unwrap();
+        }
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetAutoCompound { enabled: true },
             &[],
             user,
         )
         .unwrap();
+
+        let usdt_profit = ONE_6 * 10000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let inj_profit = 471000000000000000000u128;
+        // only the headroom under the max tokens (45 - 20 inj) can be compounded
+        let headroom = 25 * ONE_18;
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+
+        assert!(rewards.auto_compound);
+        assert_approx_eq_uint128(rewards.total_compounded, headroom.into(), 500);
+        assert_approx_eq_uint128(
+            rewards.pending_reward,
+            (inj_profit / 2 - headroom).into(),
+            500,
+        );
+        assert_eq!(rewards.total_harvested, Uint128::zero());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+
+        assert_approx_eq_uint128(
+            user_account.deposited,
+            (deposited_amount + headroom).into(),
+            500,
+        );
+
+        let user2_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user2.address(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(user2_account.deposited, Uint128::new(deposited_amount));
+        assert_approx_eq_uint128(user2_account.pending_reward, (inj_profit / 2).into(), 500);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(rewards.pending_reward, Uint128::zero());
+        assert_approx_eq_uint128(
+            rewards.total_harvested,
+            (inj_profit / 2 - headroom).into(),
+            500,
+        );
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+
+        assert_approx_eq_uint128(state.compounding_supply, user_account.deposited, 1);
+    }
+
+    #[test]
+    fn compounding_user_withdraws_the_whole_compounded_balance() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let initial_inj = 100 * ONE_18;
+        let accounts = &app
+            .init_accounts(&[Coin::new(initial_inj, "inj")], 2)
+            .unwrap();
+        let user = &accounts[0];
+        let user2 = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(3 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let exchange = Exchange::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        for u in [user, user2] {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(deposited_amount, "inj")],
+                u,
+            )
+            .unwrap();
+        }
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetAutoCompound { enabled: true },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let usdt_profit = ONE_6 * 10000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // the queried balance includes the reward compounded since the last update
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert!(user_account.deposited > Uint128::new(deposited_amount));
+
+        let r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: user_account.deposited + Uint128::one(),
+                recipient: None,
+            },
+            &[],
+            user,
+        );
+        assert!(r.is_err(), "Expected insufficient funds error");
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains(ContractError::InsufficientFunds {}.to_string().as_str()));
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: user_account.deposited,
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::zero());
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        // only the rounding of the compounded principal is left besides user2 deposit
+        assert_approx_eq_uint128(state.total_supply, deposited_amount.into(), 2);
+        assert!(state.compounding_supply <= Uint128::new(2));
+    }
+
+    #[test]
+    fn request_withdraw_and_claim_after_auction() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        // We set the blockchain time inside the withdraw buffer
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        let claim_r =
+            wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::ClaimWithdraw {}, &[], user);
+        assert!(claim_r.is_err());
+        assert!(
+            claim_r
+                .unwrap_err()
+                .to_string()
+                .contains("Withdraw request not found"),
+            "incorrect query result error message"
+        );
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::RequestWithdraw {
+                amount: deposited_amount.into(),
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::zero());
+        assert_eq!(state.requested_withdrawals, Uint128::new(deposited_amount));
+
+        let withdraw_request = wasm
+            .query::<QueryMsg, Option<WithdrawRequest>>(
+                &contract_addr,
+                &QueryMsg::WithdrawRequest {
+                    address: user.address(),
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(withdraw_request.amount, Uint128::new(deposited_amount));
+        assert_eq!(
+            withdraw_request.unlock_time,
+            u64::try_from(auction_end_time).unwrap()
+        );
+
+        let claim_r =
+            wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::ClaimWithdraw {}, &[], user);
+        assert!(claim_r.is_err());
+        assert!(
+            claim_r
+                .unwrap_err()
+                .to_string()
+                .contains("Withdraw request is locked"),
+            "incorrect query result error message"
+        );
+
+        app.increase_time(6);
+
+        let balance_before = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: "inj".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::ClaimWithdraw {}, &[], user)
+            .unwrap();
+
+        let balance_after = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: "inj".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        // the claim fee is paid from the user balance
+        assert!(balance_after > balance_before + deposited_amount - ONE_18);
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.requested_withdrawals, Uint128::zero());
+
+        let withdraw_request = wasm
+            .query::<QueryMsg, Option<WithdrawRequest>>(
+                &contract_addr,
+                &QueryMsg::WithdrawRequest {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert!(withdraw_request.is_none());
+    }
+
+    #[test]
+    fn deposit_and_withdraw_on_behalf_of_recipient() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 3)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+        let user2 = &accounts[2];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        // admin deposits on behalf of user
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: Some(user.address()),
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            admin,
+        )
+        .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(deposited_amount));
+
+        let admin_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: admin.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(admin_account.deposited, Uint128::zero());
+
+        let user2_balance_before = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user2.address(),
+                denom: "inj".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        // user withdraws to user2
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: deposited_amount.into(),
+                recipient: Some(user2.address()),
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let user2_balance_after = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user2.address(),
+                denom: "inj".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+
+        assert_eq!(
+            user2_balance_after - user2_balance_before,
+            deposited_amount,
+            "recipient balance not increased"
+        );
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::zero());
+    }
+
+    #[test]
+    fn transfer_position_to_another_address() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 3)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+        let user2 = &accounts[2];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let transfer_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TransferPosition {
+                to: user.address(),
+                amount: deposited_amount.into(),
+            },
+            &[],
+            user,
+        );
+        assert!(transfer_r.is_err());
+        assert!(
+            transfer_r
+                .unwrap_err()
+                .to_string()
+                .contains("Cannot transfer a position to the same address"),
+            "incorrect query result error message"
+        );
+
+        let transfer_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TransferPosition {
+                to: user2.address(),
+                amount: (deposited_amount + 1).into(),
+            },
+            &[],
+            user,
+        );
+        assert!(transfer_r.is_err());
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TransferPosition {
+                to: user2.address(),
+                amount: (deposited_amount / 2).into(),
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(deposited_amount / 2));
+
+        let user2_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user2.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user2_account.deposited, Uint128::new(deposited_amount / 2));
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::new(deposited_amount));
+    }
+
+    #[test]
+    fn locked_deposit_cannot_be_withdrawn_before_expiry() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(2 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        // there is no 2 rounds tier
+        let deposit_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: Some(2),
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        );
+        assert!(deposit_r.is_err());
+        assert!(
+            deposit_r
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid lock tier"),
+            "incorrect query result error message"
+        );
+
+        // nobody can lock a deposit for someone else
+        let deposit_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: Some(admin.address()),
+                lock_rounds: Some(4),
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        );
+        assert!(deposit_r.is_err());
+        assert!(
+            deposit_r
+                .unwrap_err()
+                .to_string()
+                .contains("Deposits for another address cannot be locked"),
+            "incorrect query result error message"
+        );
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: Some(4),
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(2 * deposited_amount));
+        assert_eq!(user_account.locked, Uint128::new(deposited_amount));
+        // 4 rounds tier has a 1.25 multiplier
+        assert_eq!(user_account.lock_bonus, Uint128::new(deposited_amount / 4));
+        assert_eq!(
+            user_account.lock_until_round,
+            current_auction_response.auctionRound + 4
+        );
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::new(2 * deposited_amount));
+        assert_eq!(state.total_lock_bonus, Uint128::new(deposited_amount / 4));
+
+        // auto compounding is not available for locked positions
+        let auto_compound_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetAutoCompound { enabled: true },
+            &[],
+            user,
+        );
+        assert!(auto_compound_r.is_err());
+
+        let withdraw_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: (deposited_amount + 1).into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        );
+        assert!(withdraw_r.is_err());
+        assert!(
+            withdraw_r
+                .unwrap_err()
+                .to_string()
+                .contains("Position is locked until round"),
+            "incorrect query result error message"
+        );
+
+        // the unlocked part can be withdrawn
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: deposited_amount.into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(deposited_amount));
+        assert_eq!(user_account.locked, Uint128::new(deposited_amount));
+    }
+
+    #[test]
+    fn referrer_earns_a_share_of_referee_rewards() {
+        let app = init();
+        let admin_initial_inj = 10000000 * ONE_18;
+        let initial_inj = 100 * ONE_18;
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(admin_initial_inj, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(initial_inj, "inj")], 2)
+            .unwrap();
+        let user = &accounts[0];
+        let referrer = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(3 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let exchange = Exchange::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        // referring yourself is not allowed
+        let deposit_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: Some(user.address()),
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        );
+        assert!(deposit_r.is_err());
+        assert!(
+            deposit_r
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid referrer"),
+            "incorrect query result error message"
+        );
+
+        // nor choosing the referrer of someone else
+        let deposit_r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: Some(user.address()),
+                lock_rounds: None,
+                referrer: Some(referrer.address()),
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            referrer,
+        );
+        assert!(deposit_r.is_err());
+        assert!(
+            deposit_r
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid referrer"),
+            "incorrect query result error message"
+        );
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: Some(referrer.address()),
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let usdt_profit = ONE_6 * 10000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let inj_profit = 471000000000000000000u128;
+
+        // the referrer share (10%) is taken when the referee rewards are updated
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(rewards.total_harvested, (inj_profit * 9 / 10).into(), 500);
+
+        let referrals = wasm
+            .query::<QueryMsg, ReferralsResponse>(
+                &contract_addr,
+                &QueryMsg::Referrals {
+                    referrer: referrer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(referrals.referees.len(), 1);
+        assert_eq!(referrals.referees[0].address, user.address());
+        assert_approx_eq_uint128(referrals.referees[0].earned, (inj_profit / 10).into(), 500);
+        assert_eq!(referrals.pending, referrals.referees[0].earned);
+        assert_eq!(referrals.total_earned, referrals.referees[0].earned);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], referrer)
+            .unwrap();
+
+        let referrals = wasm
+            .query::<QueryMsg, ReferralsResponse>(
+                &contract_addr,
+                &QueryMsg::Referrals {
+                    referrer: referrer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(referrals.pending, Uint128::zero());
+        assert_eq!(referrals.total_earned, referrals.referees[0].earned);
+    }
+
+    #[test]
+    fn user_history_records_deposits_and_withdrawals() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Withdraw {
+                amount: (deposited_amount / 2).into(),
+                recipient: None,
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let history = wasm
+            .query::<QueryMsg, UserHistoryResponse>(
+                &contract_addr,
+                &QueryMsg::UserHistory {
+                    address: user.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.entries[0].id, 1);
+        assert_eq!(history.entries[0].action, HistoryAction::Deposit);
+        assert_eq!(history.entries[0].amount, Uint128::new(deposited_amount));
+        assert_eq!(history.entries[1].id, 2);
+        assert_eq!(history.entries[1].action, HistoryAction::Withdraw);
+        assert_eq!(
+            history.entries[1].amount,
+            Uint128::new(deposited_amount / 2)
+        );
+
+        let history = wasm
+            .query::<QueryMsg, UserHistoryResponse>(
+                &contract_addr,
+                &QueryMsg::UserHistory {
+                    address: user.address(),
+                    start_after: Some(1),
+                    limit: Some(1),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].id, 2);
+    }
+
+    #[test]
+    fn solvency_covers_the_deposits() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let solvency = wasm
+            .query::<QueryMsg, SolvencyResponse>(&contract_addr, &QueryMsg::Solvency {})
+            .unwrap();
+
+        assert_eq!(solvency.bank_balance, Uint128::new(deposited_amount));
+        assert_eq!(solvency.subaccount_balance, Uint128::zero());
+        assert_eq!(solvency.active_bid, Uint128::zero());
+        assert_eq!(solvency.principal_liability, Uint128::new(deposited_amount));
+        assert_eq!(solvency.reward_liability, Uint128::zero());
+        assert!(solvency.inventory.is_empty());
+        assert_eq!(solvency.surplus, Uint128::zero());
+        assert_eq!(solvency.deficit, Uint128::zero());
+    }
+
+    #[test]
+    fn solvency_counts_the_bid_only_while_highest_bidder() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(10000000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let auction = Auction::new(&app);
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // the bid is escrowed in the auction module
+        let solvency = wasm
+            .query::<QueryMsg, SolvencyResponse>(&contract_addr, &QueryMsg::Solvency {})
+            .unwrap();
+        assert!(solvency.active_bid > Uint128::zero());
+        assert_eq!(
+            solvency.bank_balance + solvency.active_bid,
+            Uint128::new(deposited_amount)
+        );
+        assert_eq!(solvency.deficit, Uint128::zero());
+
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: ONE_18.to_string(),
+                        denom: "inj".to_string(),
+                    }),
+                    round: current_auction_round,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        // outbid, the bid is refunded to the bank before the attempt is cleared
+        let solvency = wasm
+            .query::<QueryMsg, SolvencyResponse>(&contract_addr, &QueryMsg::Solvency {})
+            .unwrap();
+        assert_eq!(solvency.active_bid, Uint128::zero());
+        assert_eq!(solvency.bank_balance, Uint128::new(deposited_amount));
+        assert_eq!(solvency.surplus, Uint128::zero());
+        assert_eq!(solvency.deficit, Uint128::zero());
+    }
+
+    #[test]
+    fn deposit_with_swap_from_usdt() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                2,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let exchange = Exchange::new(&app);
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(100 * ONE_18, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetRoute {
+                source_denom: "usdt".to_string(),
+                target_denom: "inj".to_string(),
+                market_id: market_id.clone(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let usdt_amount = ONE_6 * 1000;
+        let inj_out = 47100000000000000000u128;
+
+        // usdt already held by the contract, e.g. basket assets not sold yet
+        let held_usdt = ONE_6 * 5;
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin::new(held_usdt, "usdt".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        // the swap output is below the minimum, the whole deposit is reverted
+        let r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::DepositWithSwap {
+                min_out: (2 * inj_out).into(),
+            },
+            &[Coin::new(usdt_amount, "usdt")],
+            user,
+        );
+        assert!(r.is_err());
+        assert!(
+            r.unwrap_err().to_string().contains("is below the minimum"),
+            "incorrect error message"
+        );
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::DepositWithSwap {
+                min_out: (inj_out / 2).into(),
+            },
+            &[Coin::new(usdt_amount, "usdt")],
+            user,
+        )
+        .unwrap();
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(user_account.deposited, inj_out.into(), 500);
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, user_account.deposited);
+
+        // the usdt the order didn't spend went back to the user, the contract keeps only what it held
+        let contract_usdt = bank
+            .query_balance(&QueryBalanceRequest {
+                address: contract_addr.clone(),
+                denom: "usdt".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount;
+        assert_eq!(contract_usdt, held_usdt.to_string());
+
+        let user_usdt = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: "usdt".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+        assert!(user_usdt > 100000 * ONE_6 - usdt_amount);
+    }
+
+    #[test]
+    fn withdraw_as_usdt() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(100 * ONE_18, "inj")], 1)
+            .unwrap();
+        let user = &accounts[0];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let exchange = Exchange::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(2 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_buy_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetRoute {
+                source_denom: "inj".to_string(),
+                target_denom: "usdt".to_string(),
+                market_id: market_id.clone(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        // the swap output is below the minimum, the position is kept
+        let r = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::WithdrawAs {
+                amount: deposited_amount.into(),
+                denom: "usdt".to_string(),
+                min_out: (1000000 * ONE_6).into(),
+            },
+            &[],
+            user,
+        );
+        assert!(r.is_err());
+        assert!(
+            r.unwrap_err().to_string().contains("is below the minimum"),
+            "incorrect error message"
+        );
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::new(deposited_amount));
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::WithdrawAs {
+                amount: deposited_amount.into(),
+                denom: "usdt".to_string(),
+                min_out: Uint128::one(),
+            },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let usdt_balance = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: "usdt".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount
+            .parse::<u128>()
+            .unwrap();
+        assert!(usdt_balance > 0, "no usdt received");
+
+        // the inj left over by the tick rounding of the order went back to the user
+        let contract_inj = bank
+            .query_balance(&QueryBalanceRequest {
+                address: contract_addr.clone(),
+                denom: "inj".to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap()
+            .amount;
+        assert_eq!(contract_inj, "0".to_string());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user_account.deposited, Uint128::zero());
+    }
+
+    #[test]
+    fn round_profit_vests_linearly() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                2,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let exchange = Exchange::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let vesting_secs = 1000;
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::UpdateConfig {
+                new_config: InstantiateMsg {
+                    admin: admin.address(),
+                    accepted_denom: "inj".to_string(),
+                    swap_router: router_contract_add.to_string(),
+                    bid_time_buffer: 5,
+                    withdraw_time_buffer: 18000,
+                    max_inj_offset_bps: Uint128::from(15000u128),
+                    winning_bidder_reward_bps: Uint128::from(500u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: vesting_secs,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
+                },
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let usdt_profit = ONE_6 * 1000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let vesting = wasm
+            .query::<QueryMsg, VestingResponse>(&contract_addr, &QueryMsg::Vesting {})
+            .unwrap();
+        assert!(vesting.unvested_profit > Uint128::zero());
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert!(rewards.pending_reward < vesting.unvested_profit);
+
+        // the whole profit is distributed once the period is over
+        app.increase_time(vesting_secs);
+
+        let vesting_after = wasm
+            .query::<QueryMsg, VestingResponse>(&contract_addr, &QueryMsg::Vesting {})
+            .unwrap();
+        assert_eq!(vesting_after.vested_profit, vesting.unvested_profit);
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(rewards.pending_reward, vesting.unvested_profit, 1);
+    }
+
+    #[test]
+    fn user_performance_tracks_the_realized_yield() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                2,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let exchange = Exchange::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        app.increase_time(3600);
+
+        let usdt_profit = ONE_6 * 1000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let performance = wasm
+            .query::<QueryMsg, PerformanceResponse>(
+                &contract_addr,
+                &QueryMsg::UserPerformance {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+
+        assert_eq!(performance.total_deposited, Uint128::from(deposited_amount));
+        assert_eq!(performance.total_withdrawn, Uint128::zero());
+        assert_eq!(performance.average_balance, Uint128::from(deposited_amount));
+        assert_approx_eq_uint128(performance.realized_rewards, state.accumulated_profit, 1);
+        assert!(performance.annualized_yield > Decimal256::zero());
+
+        // the harvested rewards stay realized
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+
+        let after_harvest = wasm
+            .query::<QueryMsg, PerformanceResponse>(
+                &contract_addr,
+                &QueryMsg::UserPerformance {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(after_harvest.realized_rewards, performance.realized_rewards);
+
+        let global_performance = wasm
+            .query::<QueryMsg, PerformanceResponse>(&contract_addr, &QueryMsg::GlobalPerformance {})
+            .unwrap();
+        assert_eq!(
+            global_performance.total_deposited,
+            Uint128::from(deposited_amount)
+        );
+        assert_eq!(
+            global_performance.realized_rewards,
+            state.accumulated_profit
+        );
+    }
+
+    #[test]
+    fn users_are_listed_with_their_pending_rewards() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                3,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+        let user2 = &accounts[2];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let exchange = Exchange::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(2 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        for depositor in [user, user2] {
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::Deposit {
+                    recipient: None,
+                    lock_rounds: None,
+                    referrer: None,
+                },
+                &[Coin::new(deposited_amount, "inj")],
+                depositor,
+            )
+            .unwrap();
+        }
+
+        let usdt_profit = ONE_6 * 1000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let first_page = wasm
+            .query::<QueryMsg, UsersResponse>(
+                &contract_addr,
+                &QueryMsg::Users {
+                    start_after: None,
+                    limit: Some(1),
+                },
+            )
+            .unwrap();
+        assert_eq!(first_page.users.len(), 1);
+
+        let second_page = wasm
+            .query::<QueryMsg, UsersResponse>(
+                &contract_addr,
+                &QueryMsg::Users {
+                    start_after: Some(first_page.users[0].address.clone()),
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(second_page.users.len(), 1);
+
+        let users = [first_page.users, second_page.users].concat();
+        assert!(users
+            .iter()
+            .all(|u| u.deposited == Uint128::from(deposited_amount)));
+
+        let total_pending = wasm
+            .query::<QueryMsg, Uint128>(&contract_addr, &QueryMsg::TotalPendingRewards {})
+            .unwrap();
+        let listed_pending = users[0].pending_reward + users[1].pending_reward;
+
+        // the user rewards are rounded down
+        assert!(listed_pending <= total_pending);
+        assert!(total_pending - listed_pending <= Uint128::from(2u128));
+    }
+
+    #[test]
+    fn deposit_queued_during_a_bid_earns_nothing_from_its_round() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(10000000 * ONE_18, "inj")], 3)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+        let user2 = &accounts[2];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        // nobody else bids, the contract wins the basket with the minimum bid
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user2,
+        )
+        .unwrap();
+
+        app.increase_time(10);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::TrySettle {}, &[], admin)
+            .unwrap();
+
+        let global = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert!(global.accumulated_profit > Uint128::zero());
+        assert_eq!(global.queued_deposits, Uint128::zero());
+        assert_eq!(global.total_supply, Uint128::new(2 * deposited_amount));
+
+        // the settlement processed the queue, the queued deposit joined after the distribution
+        let user2_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user2.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(user2_account.deposited, Uint128::new(deposited_amount));
+        assert_eq!(user2_account.pending_reward, Uint128::zero());
+
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(user_account.pending_reward, global.accumulated_profit, 1);
+    }
+
+    #[test]
+    fn compounding_referee_shares_the_compounded_reward() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(100 * ONE_18, "inj")], 2)
+            .unwrap();
+        let user = &accounts[0];
+        let referrer = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        // 30 inj in the basket allow 45 inj to be deposited
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(3 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let exchange = Exchange::new(&app);
+
+        let market_id = launch_realistic_inj_usdt_spot_market(&exchange, &admin);
+
+        create_realistic_inj_usdt_sell_orders_from_spreadsheet(&exchange, &market_id, &admin);
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: Some(referrer.address()),
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::SetAutoCompound { enabled: true },
+            &[],
+            user,
+        )
+        .unwrap();
+
+        let usdt_profit = ONE_6 * 10000;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: contract_addr.clone(),
+                amount: vec![Coin {
+                    amount: usdt_profit.into(),
+                    denom: "usdt".to_string(),
+                }
+                .into()],
+            },
+            &admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::ManualExchangeSwap {
+                amount: usdt_profit.into(),
+                market_id: market_id.clone(),
+                asset: "usdt".to_string(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let inj_profit = 471000000000000000000u128;
+        // only the headroom under the max tokens (45 - 10 inj) is compounded
+        let headroom = 35 * ONE_18;
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+
+        // the referrer share (10%) is taken from the compounded part as well
+        let user_account = wasm
+            .query::<QueryMsg, UserAccount>(
+                &contract_addr,
+                &QueryMsg::User {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(
+            user_account.deposited,
+            (deposited_amount + headroom * 9 / 10).into(),
+            500,
+        );
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(rewards.total_compounded, (headroom * 9 / 10).into(), 500);
+        assert_approx_eq_uint128(
+            rewards.total_harvested,
+            ((inj_profit - headroom) * 9 / 10).into(),
+            500,
+        );
+
+        let referrals = wasm
+            .query::<QueryMsg, ReferralsResponse>(
+                &contract_addr,
+                &QueryMsg::Referrals {
+                    referrer: referrer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_approx_eq_uint128(referrals.referees[0].earned, (inj_profit / 10).into(), 500);
+
+        // the share of the compounded reward left the supply and is owed to the referrer
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, user_account.deposited);
+        assert_eq!(state.compounding_supply, user_account.deposited);
+
+        let solvency = wasm
+            .query::<QueryMsg, SolvencyResponse>(&contract_addr, &QueryMsg::Solvency {})
+            .unwrap();
+        assert_eq!(solvency.deficit, Uint128::zero());
+    }
+
+    #[test]
+    fn accruals_are_recorded_per_round() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(10000000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        // the contract wins two rounds in a row without the account being saved in between
+        let mut won_rounds = vec![];
+        for _ in 0..2 {
+            let current_auction_response = wasm
+                .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                    &contract_addr,
+                    &QueryMsg::CurrentAuctionBasket {},
+                )
+                .unwrap();
+
+            let current_auction_round = current_auction_response.auctionRound;
+            let auction_end_time = current_auction_response.auctionClosingTime;
+            let current_time = app.get_block_time_seconds();
+
+            let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+            app.increase_time(time_increase);
+
+            wasm.execute::<ExecuteMsg>(
+                &contract_addr,
+                &ExecuteMsg::TryBid {
+                    round: current_auction_round,
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+
+            app.increase_time(10);
+
+            wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::TrySettle {}, &[], admin)
+                .unwrap();
+
+            won_rounds.push(current_auction_round);
+
+            // the basket of the next round
+            bank.send(
+                MsgSend {
+                    from_address: admin.address(),
+                    to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                    amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+                },
+                admin,
+            )
+            .unwrap();
+        }
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::Harvest {}, &[], user)
+            .unwrap();
+
+        let rounds = wasm
+            .query::<QueryMsg, RoundsResponse>(
+                &contract_addr,
+                &QueryMsg::Rounds {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(rounds.rounds.len(), 2);
+        assert!(rounds
+            .rounds
+            .iter()
+            .all(|round| round.result == BidResult::Win));
+
+        let history = wasm
+            .query::<QueryMsg, UserHistoryResponse>(
+                &contract_addr,
+                &QueryMsg::UserHistory {
+                    address: user.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+
+        let accruals: Vec<_> = history
+            .entries
+            .iter()
+            .filter(|entry| entry.action == HistoryAction::Accrual)
+            .collect();
+        assert_eq!(accruals.len(), 2);
+
+        // one entry for the reward of each round
+        for (accrual, round) in accruals.iter().zip(won_rounds.iter()) {
+            assert_eq!(accrual.last_settled_round, *round);
+            assert!(accrual.amount > Uint128::zero());
+        }
+
+        let rewards = wasm
+            .query::<QueryMsg, UserRewardsResponse>(
+                &contract_addr,
+                &QueryMsg::UserRewards {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            accruals[0].amount + accruals[1].amount,
+            rewards.total_harvested
+        );
+    }
+
+    #[test]
+    fn router_policy_values_unrouted_basket_assets_at_zero() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(
+                &[
+                    Coin::new(10000000 * ONE_18, "inj"),
+                    Coin::new(100000 * ONE_6, "usdt"),
+                ],
+                1,
+            )
+            .unwrap();
+
+        let admin = &accounts[0];
+
+        let accounts = &app
+            .init_accounts(&[Coin::new(100 * ONE_18, "inj")], 1)
+            .unwrap();
+        let user = &accounts[0];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+
+        let deposited_amount = 10 * ONE_18;
+
+        // there is no usdt route, only the inj of the basket has a value
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![
+                    Coin::new(deposited_amount, "inj".to_string()).into(),
+                    Coin::new(10000 * ONE_6, "usdt".to_string()).into(),
+                ],
+            },
+            admin,
+        )
+        .unwrap();
+
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::UpdateConfig {
+                new_config: InstantiateMsg {
+                    admin: admin.address(),
+                    accepted_denom: "inj".to_string(),
+                    swap_router: router_contract_add.to_string(),
+                    bid_time_buffer: 5,
+                    withdraw_time_buffer: 18000,
+                    max_inj_offset_bps: Uint128::from(15000u128),
+                    winning_bidder_reward_bps: Uint128::from(500u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::zero(),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Router,
+                },
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let current_value = wasm
+            .query::<QueryMsg, Uint128>(&contract_addr, &QueryMsg::RouterCurrentAuctionValue {})
+            .unwrap();
+        assert_eq!(current_value, Uint128::new(deposited_amount));
+
+        let max_tokens = wasm
+            .query::<QueryMsg, Uint128>(&contract_addr, &QueryMsg::MaxAllowedTokensToDeposit {})
+            .unwrap();
+        assert_eq!(max_tokens, Uint128::new(deposited_amount * 3 / 2));
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        // the bid is sized on the inj part of the basket
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let solvency = wasm
+            .query::<QueryMsg, SolvencyResponse>(&contract_addr, &QueryMsg::Solvency {})
+            .unwrap();
+        assert!(solvency.active_bid > Uint128::zero());
+        assert!(solvency.active_bid <= Uint128::new(deposited_amount));
+    }
+
+    #[test]
+    fn round_cleared_then_won_is_counted_once() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(10000000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let auction = Auction::new(&app);
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(2 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: ONE_18.to_string(),
+                        denom: "inj".to_string(),
+                    }),
+                    round: current_auction_round,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryClearCurrentBid {},
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // the dao bids again in the same round and wins it
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        app.increase_time(10);
+
+        wasm.execute::<ExecuteMsg>(&contract_addr, &ExecuteMsg::TrySettle {}, &[], admin)
+            .unwrap();
+
+        // the settled record replaced the cleared one
+        let rounds = wasm
+            .query::<QueryMsg, RoundsResponse>(
+                &contract_addr,
+                &QueryMsg::Rounds {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(rounds.rounds.len(), 1);
+        assert_eq!(rounds.rounds[0].round, current_auction_round);
+        assert_eq!(rounds.rounds[0].result, BidResult::Win);
+        assert!(rounds.rounds[0].bid_amount > Uint128::new(ONE_18));
+
+        let stats = wasm
+            .query::<QueryMsg, StatsResponse>(&contract_addr, &QueryMsg::Stats {})
+            .unwrap();
+        assert_eq!(stats.rounds_participated, 1);
+        assert_eq!(stats.rounds_won, 1);
+        assert_eq!(stats.total_bid, rounds.rounds[0].bid_amount);
     }
 }
@@ -4,7 +4,11 @@ pub mod tests {
 
     use std::{env, path::PathBuf, str::FromStr};
 
-    use auction_dao::{msg::InstantiateMsg, types::InstantiateHelixRouterMsg};
+    use auction_dao::{
+        msg::InstantiateMsg,
+        state::{BidStrategy, LockTier, ValuationPolicy},
+        types::InstantiateHelixRouterMsg,
+    };
     use cosmwasm_std::{Addr, Coin, Decimal256, Uint128};
     use injective_cosmwasm::get_default_subaccount_id_for_checked_address;
     use injective_math::scale::Scaled;
@@ -356,6 +360,23 @@ pub mod tests {
         );
     }
 
+    pub fn default_lock_tiers() -> Vec<LockTier> {
+        vec![
+            LockTier {
+                rounds: 1,
+                multiplier_bps: Uint128::from(11000u128),
+            },
+            LockTier {
+                rounds: 4,
+                multiplier_bps: Uint128::from(12500u128),
+            },
+            LockTier {
+                rounds: 12,
+                multiplier_bps: Uint128::from(15000u128),
+            },
+        ]
+    }
+
     pub fn init() -> InjectiveTestApp {
         let app = InjectiveTestApp::new();
 
@@ -435,6 +456,11 @@ pub mod tests {
                     withdraw_time_buffer: 18000,
                     max_inj_offset_bps: Uint128::from(15000u128),
                     winning_bidder_reward_bps: Uint128::from(500u128),
+                    lock_tiers: default_lock_tiers(),
+                    referral_reward_bps: Uint128::from(1000u128),
+                    reward_vesting_secs: 0,
+                    bid_strategy: BidStrategy::default(),
+                    valuation_policy: ValuationPolicy::Exchange,
                 },
                 None,
                 Some("auction_dao_inj"),
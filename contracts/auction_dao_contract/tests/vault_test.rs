@@ -0,0 +1,283 @@
+mod util;
+
+mod tests {
+
+    use crate::util::tests::{
+        init, init_contract_inj, init_router_contract_inj, AUCTION_VAULT_ADDRESS, ONE_18,
+    };
+    use auction_dao::{
+        msg::{ExecuteMsg, QueryMsg, VaultResponse},
+        state::{Global, QueuedAction},
+    };
+
+    use cosmwasm_std::{Coin, Uint128};
+    use injective_std::types::{
+        cosmos::{
+            bank::v1beta1::{MsgSend, QueryBalanceRequest},
+            base::v1beta1::Coin as BidCoin,
+        },
+        injective::auction::v1beta1::{MsgBid, QueryCurrentAuctionBasketResponse},
+    };
+    use injective_test_tube::{Auction, Bank, InjectiveTestApp, Wasm};
+    use test_tube_inj::{Account, Module};
+
+    #[test]
+    fn vault_deposit_and_withdraw_with_receipt() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        // vault deposit should fail before the receipt denom exists
+        let deposit_response = wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::VaultDeposit {},
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        );
+        assert!(deposit_response.is_err());
+        assert!(
+            deposit_response
+                .unwrap_err()
+                .to_string()
+                .contains("Receipt denom not created"),
+            "incorrect query result error message"
+        );
+
+        // the admin pays for the denom creation fee
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::CreateReceiptDenom {},
+            &[Coin::new(10 * ONE_18, "inj")],
+            admin,
+        )
+        .unwrap();
+
+        let vault = wasm
+            .query::<QueryMsg, VaultResponse>(&contract_addr, &QueryMsg::Vault {})
+            .unwrap();
+        let receipt_denom = vault.receipt_denom.unwrap();
+        assert_eq!(receipt_denom, format!("factory/{}/adinj", contract_addr));
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::VaultDeposit {},
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let receipt_balance = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: receipt_denom.clone(),
+            })
+            .unwrap();
+        assert_eq!(
+            receipt_balance.balance.unwrap().amount,
+            deposited_amount.to_string()
+        );
+
+        let vault = wasm
+            .query::<QueryMsg, VaultResponse>(&contract_addr, &QueryMsg::Vault {})
+            .unwrap();
+        assert_eq!(vault.total_shares, Uint128::new(deposited_amount));
+        assert_eq!(vault.total_assets, Uint128::new(deposited_amount));
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::new(deposited_amount));
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::VaultWithdraw {},
+            &[Coin::new(deposited_amount, receipt_denom.clone())],
+            user,
+        )
+        .unwrap();
+
+        let receipt_balance = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: receipt_denom,
+            })
+            .unwrap();
+        assert_eq!(receipt_balance.balance.unwrap().amount, "0".to_string());
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.total_supply, Uint128::zero());
+        assert_eq!(state.total_shares, Uint128::zero());
+    }
+
+    #[test]
+    fn vault_deposit_is_queued_during_a_bid() {
+        let app = init();
+        let accounts = &app
+            .init_accounts(&[Coin::new(1000 * ONE_18, "inj")], 2)
+            .unwrap();
+
+        let admin = &accounts[0];
+        let user = &accounts[1];
+
+        let wasm: Wasm<'_, InjectiveTestApp> = Wasm::new(&app);
+        let bank = Bank::new(&app);
+        let auction = Auction::new(&app);
+        let router_contract_add = init_router_contract_inj(&wasm, admin);
+        let contract_addr = init_contract_inj(&wasm, admin, &router_contract_add);
+
+        let deposited_amount = 10 * ONE_18;
+
+        bank.send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: AUCTION_VAULT_ADDRESS.to_string(),
+                amount: vec![Coin::new(3 * deposited_amount, "inj".to_string()).into()],
+            },
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::CreateReceiptDenom {},
+            &[Coin::new(10 * ONE_18, "inj")],
+            admin,
+        )
+        .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::Deposit {
+                recipient: None,
+                lock_rounds: None,
+                referrer: None,
+            },
+            &[Coin::new(deposited_amount, "inj")],
+            admin,
+        )
+        .unwrap();
+
+        let current_auction_response = wasm
+            .query::<QueryMsg, QueryCurrentAuctionBasketResponse>(
+                &contract_addr,
+                &QueryMsg::CurrentAuctionBasket {},
+            )
+            .unwrap();
+
+        let current_auction_round = current_auction_response.auctionRound;
+        let auction_end_time = current_auction_response.auctionClosingTime;
+        let current_time = app.get_block_time_seconds();
+
+        let time_increase = u64::try_from(auction_end_time - current_time - 5).unwrap();
+        app.increase_time(time_increase);
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryBid {
+                round: current_auction_round,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // the deposit waits in the queue, no shares are minted yet
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::VaultDeposit {},
+            &[Coin::new(deposited_amount, "inj")],
+            user,
+        )
+        .unwrap();
+
+        let queued_action = wasm
+            .query::<QueryMsg, QueuedAction>(
+                &contract_addr,
+                &QueryMsg::QueuedActions {
+                    address: user.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(queued_action.vault_deposit, Uint128::new(deposited_amount));
+
+        let vault = wasm
+            .query::<QueryMsg, VaultResponse>(&contract_addr, &QueryMsg::Vault {})
+            .unwrap();
+        let receipt_denom = vault.receipt_denom.unwrap();
+        assert_eq!(vault.total_shares, Uint128::zero());
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.queued_deposits, Uint128::new(deposited_amount));
+
+        // somebody outbids the contract, clearing the bid processes the queue
+        auction
+            .msg_bid(
+                MsgBid {
+                    bid_amount: Some(BidCoin {
+                        amount: ONE_18.to_string(),
+                        denom: "inj".to_string(),
+                    }),
+                    round: current_auction_round,
+                    sender: admin.address(),
+                },
+                admin,
+            )
+            .unwrap();
+
+        wasm.execute::<ExecuteMsg>(
+            &contract_addr,
+            &ExecuteMsg::TryClearCurrentBid {},
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let receipt_balance = bank
+            .query_balance(&QueryBalanceRequest {
+                address: user.address(),
+                denom: receipt_denom,
+            })
+            .unwrap();
+        assert_eq!(
+            receipt_balance.balance.unwrap().amount,
+            deposited_amount.to_string()
+        );
+
+        let vault = wasm
+            .query::<QueryMsg, VaultResponse>(&contract_addr, &QueryMsg::Vault {})
+            .unwrap();
+        assert_eq!(vault.total_shares, Uint128::new(deposited_amount));
+        assert_eq!(vault.total_assets, Uint128::new(deposited_amount));
+
+        let state = wasm
+            .query::<QueryMsg, Global>(&contract_addr, &QueryMsg::State {})
+            .unwrap();
+        assert_eq!(state.queued_deposits, Uint128::zero());
+        assert_eq!(state.total_supply, Uint128::new(2 * deposited_amount));
+    }
+}
@@ -1,6 +1,6 @@
 use std::{num::TryFromIntError, str::Utf8Error};
 
-use cosmwasm_std::{ConversionOverflowError, Decimal256RangeExceeded, StdError};
+use cosmwasm_std::{ConversionOverflowError, Decimal256RangeExceeded, StdError, Uint128};
 use prost::{DecodeError, EncodeError};
 use thiserror::Error;
 
@@ -84,12 +84,84 @@ pub enum ContractError {
     #[error("The next minimum bid is to high to be worth it")]
     MinBidToHigh(),
 
+    #[error("Bid of {bid} expects a profit of {expected_profit}, {required_profit} is required")]
+    BidMarginTooLow {
+        bid: Uint128,
+        expected_profit: Uint128,
+        required_profit: Uint128,
+    },
+
+    #[error("Bid of {bid} is above the max bid of {max_bid}")]
+    BidAboveMax { bid: Uint128, max_bid: Uint128 },
+
+    #[error("Bid of {bid} is above the {max_bid} allowed for the total supply")]
+    BidAboveSupplyShare { bid: Uint128, max_bid: Uint128 },
+
+    #[error("Bid of {bid} is above the bid capacity of {capacity}")]
+    InsufficientBidCapacity { bid: Uint128, capacity: Uint128 },
+
+    #[error("Router value {router_value} and exchange value {exchange_value} diverge by {divergence_bps} bps, max {max_divergence_bps}")]
+    ValuationDivergence {
+        router_value: Uint128,
+        exchange_value: Uint128,
+        divergence_bps: Uint128,
+        max_divergence_bps: Uint128,
+    },
+
+    #[error("Invalid bid strategy")]
+    InvalidBidStrategy {},
+
     #[error("Its not yet time buddy")]
     NotInBidTime {},
 
     #[error("Withdraw is disabled {0} minutes before the auctions end (the auction ends in {1} minutes)")]
     NotInWithdrawTime(u64, u64),
 
+    #[error("Withdraw request not found")]
+    WithdrawRequestNotFound {},
+
+    #[error("Withdraw request is locked until {0}")]
+    WithdrawRequestLocked(u64),
+
+    #[error("Invalid lock tier")]
+    InvalidLockTier {},
+
+    #[error("Position is already locked with another tier")]
+    LockTierMismatch {},
+
+    #[error("Deposits for another address cannot be locked")]
+    LockForRecipient {},
+
+    #[error("Locked positions cannot be auto compounded")]
+    LockWithAutoCompound {},
+
+    #[error("Position is locked until round {0}")]
+    PositionLocked(u64),
+
+    #[error("Invalid referrer")]
+    InvalidReferrer {},
+
+    #[error("Invalid referral reward bps")]
+    InvalidReferralRewardBps {},
+
+    #[error("Cannot transfer a position to the same address")]
+    TransferToSelf {},
+
+    #[error("Contract is shut down")]
+    Shutdown {},
+
+    #[error("Swap output {0} is below the minimum {1}")]
+    SwapOutputTooLow(Uint128, Uint128),
+
+    #[error("Receipt denom not created")]
+    ReceiptDenomNotCreated {},
+
+    #[error("Receipt denom already created")]
+    ReceiptDenomAlreadyCreated {},
+
+    #[error("Vault assets were lost, the outstanding shares can't be priced")]
+    VaultDepleted {},
+
     #[error("Migration error")]
     MigrationError {},
 
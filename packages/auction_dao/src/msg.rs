@@ -1,9 +1,11 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
-use crate::state::BidAttempt;
+use crate::state::{
+    BidAttempt, BidStrategy, HistoryAction, LockTier, RoundRecord, ValuationPolicy,
+};
 #[allow(unused_imports)]
-use crate::state::{Config, Global, UserAccount};
-use cosmwasm_std::Uint128;
+use crate::state::{Config, Global, QueuedAction, UserAccount, WithdrawRequest};
+use cosmwasm_std::{Coin, Decimal256, SignedDecimal256, Uint128};
 #[allow(unused_imports)]
 use injective_std::types::injective::auction::v1beta1::QueryCurrentAuctionBasketResponse;
 
@@ -16,20 +18,62 @@ pub struct InstantiateMsg {
     pub withdraw_time_buffer: u64,
     pub winning_bidder_reward_bps: Uint128,
     pub max_inj_offset_bps: Uint128,
+    // the fields below default to the behaviour before they were added, so the older
+    // instantiate and update_config messages are still accepted
+    #[serde(default)]
+    pub lock_tiers: Vec<LockTier>,
+    #[serde(default)]
+    pub referral_reward_bps: Uint128,
+    #[serde(default)]
+    pub reward_vesting_secs: u64,
+    #[serde(default)]
+    pub bid_strategy: BidStrategy,
+    #[serde(default)]
+    pub valuation_policy: ValuationPolicy,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    Deposit {},
+    Deposit {
+        recipient: Option<String>,
+        lock_rounds: Option<u64>,
+        referrer: Option<String>,
+    },
+    DepositWithSwap {
+        min_out: Uint128,
+    },
     Harvest {},
     Withdraw {
         amount: Uint128,
+        recipient: Option<String>,
+    },
+    WithdrawAs {
+        amount: Uint128,
+        denom: String,
+        min_out: Uint128,
+    },
+    RequestWithdraw {
+        amount: Uint128,
+    },
+    ClaimWithdraw {},
+    TransferPosition {
+        to: String,
+        amount: Uint128,
+    },
+    SetAutoCompound {
+        enabled: bool,
     },
+    VaultDeposit {},
+    VaultWithdraw {},
+    CreateReceiptDenom {},
     TryBid {
         round: u64,
     },
     TrySettle {},
     TryClearCurrentBid {},
+    ProcessQueue {
+        limit: Option<u32>,
+    },
     UpdateConfig {
         new_config: InstantiateMsg,
     },
@@ -47,6 +91,7 @@ pub enum ExecuteMsg {
         market_id: String,
         asset: String,
     },
+    Shutdown {},
     Callback(CallbackMsg),
 }
 
@@ -78,6 +123,216 @@ pub enum QueryMsg {
     RouterCurrentAuctionValue {},
     #[returns(Uint128)]
     MaxAllowedTokensToDeposit {},
+    #[returns(BasketValuationResponse)]
+    BasketValuation {},
+    #[returns(BidCapacityResponse)]
+    BidCapacity {},
+    #[returns(VaultResponse)]
+    Vault {},
+    #[returns(UserRewardsResponse)]
+    UserRewards { address: String },
+    #[returns(QueuedAction)]
+    QueuedActions { address: String },
+    #[returns(Option<WithdrawRequest>)]
+    WithdrawRequest { address: String },
+    #[returns(Uint128)]
+    RoundLoss { round: u64 },
+    #[returns(EligibilityResponse)]
+    Eligibility { address: String },
+    #[returns(ReferralsResponse)]
+    Referrals {
+        referrer: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(UsersResponse)]
+    Users {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Uint128)]
+    TotalPendingRewards {},
+    #[returns(SolvencyResponse)]
+    Solvency {},
+    #[returns(Uint128)]
+    AccumulatedDust {},
+    #[returns(ShutdownStatusResponse)]
+    ShutdownStatus {},
+    #[returns(VestingResponse)]
+    Vesting {},
+    #[returns(PerformanceResponse)]
+    UserPerformance { address: String },
+    #[returns(PerformanceResponse)]
+    GlobalPerformance {},
+    #[returns(StatsResponse)]
+    Stats {},
+    #[returns(RoundsResponse)]
+    Rounds {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(UserHistoryResponse)]
+    UserHistory {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct BidCapacityResponse {
+    // accepted denom held by the contract
+    pub balance: Uint128,
+    // principal and undistributed profit, without the queued withdrawals
+    pub pooled_funds: Uint128,
+    // the lower of the two, the highest bid the contract can place
+    pub capacity: Uint128,
+}
+
+#[cw_serde]
+pub struct BasketValuationResponse {
+    pub router_value: Uint128,
+    pub exchange_value: Uint128,
+    // difference of the two values over the higher one
+    pub divergence_bps: Uint128,
+    pub policy: ValuationPolicy,
+    // value given by the policy, none when it rejects the divergence
+    pub value: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct VaultResponse {
+    pub receipt_denom: Option<String>,
+    pub total_shares: Uint128,
+    pub total_assets: Uint128,
+    pub exchange_rate: Decimal256,
+}
+
+#[cw_serde]
+pub struct EligibilityResponse {
+    // stake earning the profit of the active round
+    pub eligible: Uint128,
+    // queued deposits, eligible from the next round
+    pub ineligible: Uint128,
+    // round of the active bid and the weighted supply sharing its profit
+    pub round: Option<u64>,
+    pub eligible_supply: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct RefereeInfo {
+    pub address: String,
+    // referrer rewards earned from this referee
+    pub earned: Uint128,
+}
+
+#[cw_serde]
+pub struct ReferralsResponse {
+    pub pending: Uint128,
+    pub total_earned: Uint128,
+    pub referees: Vec<RefereeInfo>,
+}
+
+#[cw_serde]
+pub struct UserInfo {
+    pub address: String,
+    pub deposited: Uint128,
+    // computed with the current index
+    pub pending_reward: Uint128,
+}
+
+#[cw_serde]
+pub struct UsersResponse {
+    pub users: Vec<UserInfo>,
+}
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    // accepted denom held by the contract, in the bank, its subaccount and the active bid
+    pub bank_balance: Uint128,
+    pub subaccount_balance: Uint128,
+    pub active_bid: Uint128,
+    // deposits, queued deposits and withdraw requests
+    pub principal_liability: Uint128,
+    // distributed and undistributed profit not paid out yet
+    pub reward_liability: Uint128,
+    // other denoms held by the contract, e.g. basket assets not sold yet
+    pub inventory: Vec<Coin>,
+    pub surplus: Uint128,
+    pub deficit: Uint128,
+}
+
+#[cw_serde]
+pub struct ShutdownStatusResponse {
+    pub shutdown: bool,
+    // share of the amounts owed paid on exit, below one when the contract holds too little,
+    // none while a bid sent before the shutdown is not settled
+    pub exit_ratio: Option<Decimal256>,
+    pub unsold_assets: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct VestingResponse {
+    // profit not in the index yet, including the vested part below
+    pub unvested_profit: Uint128,
+    // part of the unvested profit added to the index by the next update
+    pub vested_profit: Uint128,
+    pub vesting_end: u64,
+}
+
+#[cw_serde]
+pub struct PerformanceResponse {
+    pub total_deposited: Uint128,
+    pub total_withdrawn: Uint128,
+    // rewards earned so far, harvested, compounded or pending
+    pub realized_rewards: Uint128,
+    pub total_loss: Uint128,
+    pub first_deposit_time: u64,
+    // balance averaged over the time since the first deposit
+    pub average_balance: Uint128,
+    // realized rewards over the average balance, per year
+    pub annualized_yield: Decimal256,
+}
+
+#[cw_serde]
+pub struct StatsResponse {
+    pub rounds_participated: u64,
+    pub rounds_won: u64,
+    pub win_rate: Decimal256,
+    pub total_bid: Uint128,
+    pub total_proceeds: Uint128,
+    pub total_profit: Uint128,
+    pub total_loss: Uint128,
+    pub total_keeper_rewards: Uint128,
+    // profit net of the losses over the bids of the won rounds
+    pub average_margin: SignedDecimal256,
+    pub largest_loss: Uint128,
+}
+
+#[cw_serde]
+pub struct RoundsResponse {
+    pub rounds: Vec<RoundRecord>,
+}
+
+#[cw_serde]
+pub struct UserHistoryEntry {
+    pub id: u64,
+    pub action: HistoryAction,
+    pub amount: Uint128,
+    pub last_settled_round: u64,
+}
+
+#[cw_serde]
+pub struct UserHistoryResponse {
+    pub entries: Vec<UserHistoryEntry>,
+}
+
+#[cw_serde]
+pub struct UserRewardsResponse {
+    pub auto_compound: bool,
+    pub pending_reward: Uint128,
+    pub total_compounded: Uint128,
+    pub total_harvested: Uint128,
 }
 
 #[cw_serde]
@@ -86,3 +341,6 @@ pub struct MigrateMsg {}
 pub const TRY_BID_SUCCESS_REPLY_ID: u64 = 1;
 pub const SELL_ASSET_SUCCESS_REPLY_ID: u64 = 2;
 pub const BID_SETTLED_SUCCESS_REPLY_ID: u64 = 3;
+pub const PROCESS_QUEUE_REPLY_ID: u64 = 4;
+pub const DEPOSIT_SWAP_SUCCESS_REPLY_ID: u64 = 5;
+pub const WITHDRAW_SWAP_SUCCESS_REPLY_ID: u64 = 6;
@@ -1,8 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal256, Uint128};
+use cosmwasm_std::{Addr, Decimal256, Uint128, Uint256};
 use injective_cosmwasm::{MarketId, SubaccountId};
 use injective_std::types::cosmos::base::v1beta1::Coin;
 
+use crate::types::BidResult;
+
 #[cw_serde]
 pub struct Config {
     pub accepted_denom: String,
@@ -13,6 +15,69 @@ pub struct Config {
     pub max_inj_offset_bps: Uint128,
     pub winning_bidder_reward_bps: Uint128,
     pub contract_subaccount_id: SubaccountId,
+    #[serde(default)]
+    pub lock_tiers: Vec<LockTier>,
+    // share of the referees rewards going to their referrer
+    #[serde(default)]
+    pub referral_reward_bps: Uint128,
+    // set by the admin to wind the DAO down, can't be undone
+    #[serde(default)]
+    pub shutdown: bool,
+    // period over which the profit of a round is added to the index, 0 adds it at once
+    #[serde(default)]
+    pub reward_vesting_secs: u64,
+    #[serde(default)]
+    pub bid_strategy: BidStrategy,
+    // basket value used for bidding and for the max tokens
+    #[serde(default)]
+    pub valuation_policy: ValuationPolicy,
+}
+
+#[cw_serde]
+pub enum ValuationPolicy {
+    Router,
+    Exchange,
+    // lower of the router and exchange values
+    Min,
+    // lower of the two values, fails when they differ by more than max_divergence_bps
+    RejectOnDivergence { max_divergence_bps: Uint128 },
+}
+
+// the router valuation used before the policy was configurable
+impl Default for ValuationPolicy {
+    fn default() -> Self {
+        ValuationPolicy::Router
+    }
+}
+
+// limits checked before bidding, zero disables a limit
+#[cw_serde]
+pub struct BidStrategy {
+    // profit required over the bid after the fees and the bidder reward
+    pub min_margin_bps: Uint128,
+    // estimated fees paid when selling the basket
+    pub taker_fee_bps: Uint128,
+    pub max_bid: Uint128,
+    // max share of the total supply bid in a round
+    pub max_supply_bps: Uint128,
+}
+
+impl Default for BidStrategy {
+    fn default() -> Self {
+        BidStrategy {
+            min_margin_bps: Uint128::zero(),
+            taker_fee_bps: Uint128::zero(),
+            max_bid: Uint128::zero(),
+            max_supply_bps: Uint128::zero(),
+        }
+    }
+}
+
+// deposits locked for `rounds` auction rounds earn with a multiplied weight
+#[cw_serde]
+pub struct LockTier {
+    pub rounds: u64,
+    pub multiplier_bps: Uint128,
 }
 
 #[cw_serde]
@@ -20,6 +85,40 @@ pub struct UserAccount {
     pub deposited: Uint128,
     pub index: Decimal256,
     pub pending_reward: Uint128,
+    #[serde(default)]
+    pub auto_compound: bool,
+    // Global compound indexes at the last update, used only when auto_compound is on
+    #[serde(default)]
+    pub compound_index: Decimal256,
+    #[serde(default)]
+    pub compound_reward_index: Decimal256,
+    #[serde(default)]
+    pub total_compounded: Uint128,
+    #[serde(default)]
+    pub total_harvested: Uint128,
+    // Global principal_factor at the last update
+    #[serde(default = "Decimal256::one")]
+    pub principal_factor: Decimal256,
+    // principal lost in rounds where the basket sold for less than the bid
+    #[serde(default)]
+    pub total_loss: Uint128,
+    // locked part of deposited and the extra reward weight it gives until lock_until_round
+    #[serde(default)]
+    pub locked: Uint128,
+    #[serde(default)]
+    pub lock_bonus: Uint128,
+    #[serde(default)]
+    pub lock_multiplier_bps: Uint128,
+    #[serde(default)]
+    pub lock_until_round: u64,
+    #[serde(default)]
+    pub referrer: Option<Addr>,
+    // referrer share of the last reward update, credited to the referrer when the account is saved
+    #[serde(default)]
+    pub referral_fee: Uint128,
+    // fraction of a reward unit not yet added to pending_reward
+    #[serde(default)]
+    pub reward_remainder: Decimal256,
 }
 
 impl Default for UserAccount {
@@ -28,6 +127,20 @@ impl Default for UserAccount {
             deposited: Uint128::zero(),
             index: Decimal256::zero(),
             pending_reward: Uint128::zero(),
+            auto_compound: false,
+            compound_index: Decimal256::zero(),
+            compound_reward_index: Decimal256::zero(),
+            total_compounded: Uint128::zero(),
+            total_harvested: Uint128::zero(),
+            principal_factor: Decimal256::one(),
+            total_loss: Uint128::zero(),
+            locked: Uint128::zero(),
+            lock_bonus: Uint128::zero(),
+            lock_multiplier_bps: Uint128::zero(),
+            lock_until_round: 0,
+            referrer: None,
+            referral_fee: Uint128::zero(),
+            reward_remainder: Decimal256::zero(),
         }
     }
 }
@@ -40,6 +153,56 @@ pub struct Global {
     // sum of the profit already distributed
     pub accumulated_profit: Uint128,
     pub total_supply: Uint128,
+    // receipt tokens minted against the vault position
+    #[serde(default)]
+    pub total_shares: Uint128,
+    // part of total_supply owned by auto compounding users
+    #[serde(default)]
+    pub compounding_supply: Uint128,
+    // growth of one unit deposited by an auto compounding user
+    #[serde(default = "Decimal256::one")]
+    pub compound_index: Decimal256,
+    // rewards per compounding unit that did not fit under the max tokens
+    #[serde(default)]
+    pub compound_reward_index: Decimal256,
+    // deposits escrowed while a bid was active, not part of total_supply yet
+    #[serde(default)]
+    pub queued_deposits: Uint128,
+    // withdrawals recorded while a bid was active, still part of total_supply
+    #[serde(default)]
+    pub queued_withdrawals: Uint128,
+    // requested withdrawals waiting to be claimed, already out of total_supply
+    #[serde(default)]
+    pub requested_withdrawals: Uint128,
+    // principal left from one unit deposited before any loss
+    #[serde(default = "Decimal256::one")]
+    pub principal_factor: Decimal256,
+    // sum of the losses taken by the depositors
+    #[serde(default)]
+    pub accumulated_loss: Uint128,
+    // extra reward weight of the locked deposits, not part of total_supply
+    #[serde(default)]
+    pub total_lock_bonus: Uint128,
+    // last auction round whose result was distributed
+    #[serde(default)]
+    pub last_settled_round: u64,
+    // rewards paid out or compounded into the principal, no longer owed as rewards
+    #[serde(default)]
+    pub rewards_settled: Uint128,
+    // sum of the rounding remainders rolled over to the next distribution, each counted
+    // once when it is left over
+    #[serde(default)]
+    pub accumulated_dust: Uint128,
+    // profit streamed into the index until vesting_end
+    #[serde(default)]
+    pub unvested_profit: Uint128,
+    #[serde(default)]
+    pub vesting_end: u64,
+    #[serde(default)]
+    pub last_vesting_time: u64,
+    // max tokens at the settlement, caps the compounding of the vested profit
+    #[serde(default)]
+    pub vesting_max_tokens: Uint128,
 }
 
 impl Default for Global {
@@ -49,6 +212,138 @@ impl Default for Global {
             profit_to_distribute: Uint128::zero(),
             accumulated_profit: Uint128::zero(),
             total_supply: Uint128::zero(),
+            total_shares: Uint128::zero(),
+            compounding_supply: Uint128::zero(),
+            compound_index: Decimal256::one(),
+            compound_reward_index: Decimal256::zero(),
+            queued_deposits: Uint128::zero(),
+            queued_withdrawals: Uint128::zero(),
+            requested_withdrawals: Uint128::zero(),
+            principal_factor: Decimal256::one(),
+            accumulated_loss: Uint128::zero(),
+            total_lock_bonus: Uint128::zero(),
+            last_settled_round: 0,
+            rewards_settled: Uint128::zero(),
+            accumulated_dust: Uint128::zero(),
+            unvested_profit: Uint128::zero(),
+            vesting_end: 0,
+            last_vesting_time: 0,
+            vesting_max_tokens: Uint128::zero(),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct ReferrerRewards {
+    // claimable with harvest
+    pub pending: Uint128,
+    pub total_earned: Uint128,
+}
+
+impl Default for ReferrerRewards {
+    fn default() -> Self {
+        ReferrerRewards {
+            pending: Uint128::zero(),
+            total_earned: Uint128::zero(),
+        }
+    }
+}
+
+#[cw_serde]
+pub enum HistoryAction {
+    Deposit,
+    Withdraw,
+    Harvest,
+    TransferIn,
+    TransferOut,
+    // rewards earned since the previous entry, net of the referrer share. The rewards are
+    // computed lazily, recorded as one entry per round settled since the account was saved
+    Accrual,
+    Loss,
+}
+
+#[cw_serde]
+pub struct HistoryEntry {
+    pub action: HistoryAction,
+    pub amount: Uint128,
+    // last settled round when the entry was recorded, for an accrual the round it was earned in
+    pub last_settled_round: u64,
+}
+
+// Kept apart from UserAccount, which is removed once the position is empty.
+// The global stats only count the deposits and withdrawals, the balance is the total supply
+#[cw_serde]
+pub struct PositionStats {
+    // deposits and transfers in
+    pub total_deposited: Uint128,
+    // withdrawn principal and transfers out
+    pub total_withdrawn: Uint128,
+    pub total_harvested: Uint128,
+    // rewards accrued net of the referrer share, harvested or not
+    pub total_earned: Uint128,
+    pub total_loss: Uint128,
+    pub balance: TimeWeightedBalance,
+}
+
+impl Default for PositionStats {
+    fn default() -> Self {
+        PositionStats {
+            total_deposited: Uint128::zero(),
+            total_withdrawn: Uint128::zero(),
+            total_harvested: Uint128::zero(),
+            total_earned: Uint128::zero(),
+            total_loss: Uint128::zero(),
+            balance: TimeWeightedBalance::default(),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct TimeWeightedBalance {
+    pub first_deposit_time: u64,
+    // sum of the balance times the seconds it was held
+    pub balance_seconds: Uint256,
+    pub last_balance: Uint128,
+    pub last_update_time: u64,
+}
+
+impl Default for TimeWeightedBalance {
+    fn default() -> Self {
+        TimeWeightedBalance {
+            first_deposit_time: 0,
+            balance_seconds: Uint256::zero(),
+            last_balance: Uint128::zero(),
+            last_update_time: 0,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct WithdrawRequest {
+    // principal plus the rewards pending at request time
+    pub amount: Uint128,
+    pub unlock_time: u64,
+}
+
+#[cw_serde]
+pub struct QueuedAction {
+    pub deposit: Uint128,
+    pub withdraw: Uint128,
+    // address receiving the queued withdraw, the owner when not set
+    #[serde(default)]
+    pub withdraw_recipient: Option<Addr>,
+    // minted as vault shares once the queue is processed
+    #[serde(default)]
+    pub vault_deposit: Uint128,
+}
+
+impl Default for QueuedAction {
+    fn default() -> Self {
+        QueuedAction {
+            deposit: Uint128::zero(),
+            withdraw: Uint128::zero(),
+            withdraw_recipient: None,
+            vault_deposit: Uint128::zero(),
         }
     }
 }
@@ -88,6 +383,57 @@ impl Default for BidAttempt {
     }
 }
 
+// Saved when the round is settled, the proceeds and the profit once the basket is sold
+#[cw_serde]
+pub struct RoundRecord {
+    pub round: u64,
+    pub bid_amount: Uint128,
+    // address that submitted the bid
+    pub keeper: Addr,
+    pub basket: Vec<Coin>,
+    pub result: BidResult,
+    pub winner: String,
+    pub winning_bid: Uint128,
+    // accepted denom received for each basket asset
+    pub proceeds: Vec<Coin>,
+    pub profit: Uint128,
+    pub loss: Uint128,
+    pub keeper_reward: Uint128,
+    // global index after the distribution
+    pub index: Decimal256,
+}
+
+// Totals of the rounds in ROUND_HISTORY, the lost bids are refunded
+#[cw_serde]
+pub struct DaoStats {
+    pub rounds_participated: u64,
+    pub rounds_won: u64,
+    pub total_bid: Uint128,
+    pub total_won_bid: Uint128,
+    pub total_proceeds: Uint128,
+    // profit before the keeper rewards
+    pub total_profit: Uint128,
+    pub total_loss: Uint128,
+    pub total_keeper_rewards: Uint128,
+    pub largest_loss: Uint128,
+}
+
+impl Default for DaoStats {
+    fn default() -> Self {
+        DaoStats {
+            rounds_participated: 0,
+            rounds_won: 0,
+            total_bid: Uint128::zero(),
+            total_won_bid: Uint128::zero(),
+            total_proceeds: Uint128::zero(),
+            total_profit: Uint128::zero(),
+            total_loss: Uint128::zero(),
+            total_keeper_rewards: Uint128::zero(),
+            largest_loss: Uint128::zero(),
+        }
+    }
+}
+
 #[cw_serde]
 pub enum SellType {
     Base,
@@ -98,4 +444,31 @@ pub enum SellType {
 pub struct SellAssetPayload {
     pub coin: Coin,
     pub sell_type: SellType,
+    // round whose basket is sold, 0 for the manual swaps
+    #[serde(default)]
+    pub round: u64,
+}
+
+#[cw_serde]
+pub struct DepositSwapPayload {
+    pub sender: Addr,
+    pub coin: Coin,
+    pub sell_type: SellType,
+    // minimum amount of the accepted denom to receive from the swap
+    pub min_out: Uint128,
+    // contract balance of coin.denom besides the deposit, the part above it after the swap is refunded
+    #[serde(default)]
+    pub held: Uint128,
+}
+
+#[cw_serde]
+pub struct WithdrawSwapPayload {
+    pub recipient: Addr,
+    // denom bought with the withdrawn amount
+    pub denom: String,
+    pub sell_type: SellType,
+    pub min_out: Uint128,
+    // contract balance of the accepted denom besides the payout, the part above it after the swap is sent back
+    #[serde(default)]
+    pub held: Uint128,
 }